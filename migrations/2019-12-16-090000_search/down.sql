@@ -0,0 +1,16 @@
+-- This file should undo anything in `up.sql`
+
+DROP TRIGGER IF EXISTS ruts_tsv_update ON ruts;
+DROP TRIGGER IF EXISTS items_tsv_update ON items;
+DROP TRIGGER IF EXISTS tags_tsv_update ON tags;
+DROP TRIGGER IF EXISTS etcs_tsv_update ON etcs;
+
+DROP FUNCTION IF EXISTS ruts_tsv_trigger();
+DROP FUNCTION IF EXISTS items_tsv_trigger();
+DROP FUNCTION IF EXISTS tags_tsv_trigger();
+DROP FUNCTION IF EXISTS etcs_tsv_trigger();
+
+ALTER TABLE ruts DROP COLUMN IF EXISTS tsv;
+ALTER TABLE items DROP COLUMN IF EXISTS tsv;
+ALTER TABLE tags DROP COLUMN IF EXISTS tsv;
+ALTER TABLE etcs DROP COLUMN IF EXISTS tsv;
@@ -0,0 +1,67 @@
+-- Your SQL goes here
+
+-- full-text search: tsvector per searchable table, kept by trigger
+-- tsv is not in schema.rs, query it via sql_query only
+
+ALTER TABLE ruts ADD COLUMN tsv TSVECTOR;
+ALTER TABLE items ADD COLUMN tsv TSVECTOR;
+ALTER TABLE tags ADD COLUMN tsv TSVECTOR;
+ALTER TABLE etcs ADD COLUMN tsv TSVECTOR;
+
+CREATE OR REPLACE FUNCTION ruts_tsv_trigger() RETURNS trigger AS $$
+BEGIN
+  NEW.tsv :=
+    setweight(to_tsvector('english', coalesce(NEW.title, '')), 'A') ||
+    setweight(to_tsvector('english', coalesce(NEW.author, '')), 'B') ||
+    setweight(to_tsvector('english', coalesce(NEW.content, '')), 'C');
+  RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION items_tsv_trigger() RETURNS trigger AS $$
+BEGIN
+  NEW.tsv :=
+    setweight(to_tsvector('english', coalesce(NEW.title, '')), 'A') ||
+    setweight(to_tsvector('english', coalesce(NEW.uiid, '')), 'A') ||
+    setweight(to_tsvector('english', coalesce(NEW.authors, '')), 'B') ||
+    setweight(to_tsvector('english', coalesce(NEW.publisher, '')), 'C') ||
+    setweight(to_tsvector('english', coalesce(NEW.detail, '')), 'C');
+  RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION tags_tsv_trigger() RETURNS trigger AS $$
+BEGIN
+  NEW.tsv :=
+    setweight(to_tsvector('english', coalesce(NEW.tname, '')), 'A') ||
+    setweight(to_tsvector('english', coalesce(NEW.intro, '')), 'C');
+  RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION etcs_tsv_trigger() RETURNS trigger AS $$
+BEGIN
+  NEW.tsv := setweight(to_tsvector('english', coalesce(NEW.content, '')), 'C');
+  RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER ruts_tsv_update BEFORE INSERT OR UPDATE ON ruts
+  FOR EACH ROW EXECUTE PROCEDURE ruts_tsv_trigger();
+CREATE TRIGGER items_tsv_update BEFORE INSERT OR UPDATE ON items
+  FOR EACH ROW EXECUTE PROCEDURE items_tsv_trigger();
+CREATE TRIGGER tags_tsv_update BEFORE INSERT OR UPDATE ON tags
+  FOR EACH ROW EXECUTE PROCEDURE tags_tsv_trigger();
+CREATE TRIGGER etcs_tsv_update BEFORE INSERT OR UPDATE ON etcs
+  FOR EACH ROW EXECUTE PROCEDURE etcs_tsv_trigger();
+
+-- fill existing rows via the triggers
+UPDATE ruts SET tsv = NULL;
+UPDATE items SET tsv = NULL;
+UPDATE tags SET tsv = NULL;
+UPDATE etcs SET tsv = NULL;
+
+CREATE INDEX ruts_tsv_idx ON ruts USING GIN (tsv);
+CREATE INDEX items_tsv_idx ON items USING GIN (tsv);
+CREATE INDEX tags_tsv_idx ON tags USING GIN (tsv);
+CREATE INDEX etcs_tsv_idx ON etcs USING GIN (tsv);
@@ -0,0 +1,3 @@
+-- Your SQL goes here
+
+ALTER TABLE users ADD COLUMN IF NOT EXISTS email_confirmed BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,10 @@
+-- Your SQL goes here
+
+CREATE TABLE pswresets (
+  id VARCHAR NOT NULL PRIMARY KEY,
+  uname VARCHAR NOT NULL,
+  token_hash VARCHAR NOT NULL, -- hashed, the plain token only in mail
+  expire_at TIMESTAMP NOT NULL,
+  used BOOLEAN NOT NULL DEFAULT FALSE,
+  create_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
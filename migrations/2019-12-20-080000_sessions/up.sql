@@ -0,0 +1,14 @@
+-- Your SQL goes here
+
+-- signin session, per rotating refresh token
+CREATE TABLE sessions (
+  id VARCHAR NOT NULL PRIMARY KEY,
+  uname VARCHAR NOT NULL,
+  refresh_hash VARCHAR NOT NULL, -- hashed, the plain token only to client
+  expire_at TIMESTAMP NOT NULL,
+  revoked BOOLEAN NOT NULL DEFAULT FALSE,
+  create_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  renew_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX sessions_uname_idx ON sessions (uname);
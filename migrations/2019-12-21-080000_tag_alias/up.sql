@@ -0,0 +1,12 @@
+-- Your SQL goes here
+
+CREATE TABLE tagaliases (
+  alias VARCHAR NOT NULL PRIMARY KEY,
+  tname VARCHAR NOT NULL, -- the canonical tag
+  uname VARCHAR NOT NULL, -- moderator
+  merged BOOLEAN NOT NULL DEFAULT FALSE, -- left by merge, as redirect
+  create_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  CHECK (alias != tname)
+);
+
+CREATE INDEX tagaliases_tname_idx ON tagaliases (tname);
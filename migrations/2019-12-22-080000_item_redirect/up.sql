@@ -0,0 +1,12 @@
+-- Your SQL goes here
+
+CREATE TABLE itemredirects (
+  old_slug VARCHAR NOT NULL PRIMARY KEY,
+  old_id VARCHAR NOT NULL, -- the merged item
+  item_id VARCHAR NOT NULL, -- the surviving item
+  uname VARCHAR NOT NULL, -- moderator
+  create_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX itemredirects_old_id_idx ON itemredirects (old_id);
+CREATE INDEX itemredirects_item_id_idx ON itemredirects (item_id);
@@ -0,0 +1,18 @@
+-- Your SQL goes here
+
+CREATE TABLE crawl_jobs (
+  id VARCHAR NOT NULL PRIMARY KEY,
+  url VARCHAR NOT NULL,
+  domain VARCHAR NOT NULL, -- for politeness delay
+  uname VARCHAR NOT NULL, -- who submit
+  status VARCHAR NOT NULL DEFAULT 'pending', -- pending|running|done|failed
+  attempts SMALLINT NOT NULL DEFAULT 0,
+  draft JSONB NOT NULL DEFAULT '{}', -- NewItem draft once done
+  error VARCHAR NOT NULL DEFAULT '', -- last error
+  next_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP, -- not run before, per backoff
+  create_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  update_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX crawl_jobs_status_next_idx ON crawl_jobs (status, next_at);
+CREATE INDEX crawl_jobs_url_idx ON crawl_jobs (url);
@@ -0,0 +1,9 @@
+-- This file should undo anything in `up.sql`
+
+DROP TABLE IF EXISTS itemsuggests;
+
+DROP INDEX IF EXISTS items_checked_at_idx;
+ALTER TABLE items DROP COLUMN IF EXISTS checked_at;
+ALTER TABLE items DROP COLUMN IF EXISTS dead_link;
+ALTER TABLE items DROP COLUMN IF EXISTS cover_status;
+ALTER TABLE items DROP COLUMN IF EXISTS url_status;
@@ -0,0 +1,26 @@
+-- Your SQL goes here
+
+-- link check of item url and cover
+-- status: 0 unchecked, -1 unreachable, or the http status
+ALTER TABLE items ADD COLUMN url_status SMALLINT NOT NULL DEFAULT 0;
+ALTER TABLE items ADD COLUMN cover_status SMALLINT NOT NULL DEFAULT 0;
+ALTER TABLE items ADD COLUMN dead_link BOOLEAN NOT NULL DEFAULT FALSE; -- url or cover
+ALTER TABLE items ADD COLUMN checked_at TIMESTAMP; -- null if never
+
+CREATE INDEX items_checked_at_idx ON items (checked_at NULLS FIRST);
+
+-- metadata update suggested per link check, to be approved by moderator
+CREATE TABLE itemsuggests (
+  id VARCHAR NOT NULL PRIMARY KEY,
+  item_id VARCHAR NOT NULL,
+  field VARCHAR NOT NULL, -- uiid|authors|pub_at|publisher|cover|edition
+  old_value VARCHAR NOT NULL,
+  new_value VARCHAR NOT NULL,
+  status VARCHAR NOT NULL DEFAULT 'pending', -- pending|approved|rejected
+  uname VARCHAR NOT NULL DEFAULT '', -- moderator who decide
+  create_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  decide_at TIMESTAMP
+);
+
+CREATE INDEX itemsuggests_status_idx ON itemsuggests (status, create_at);
+CREATE INDEX itemsuggests_item_id_idx ON itemsuggests (item_id);
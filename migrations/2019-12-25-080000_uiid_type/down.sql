@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+-- the isbn-10 converted are kept as isbn-13, doi and arxiv as normalized
+
+DROP INDEX IF EXISTS items_uiid_idx;
+ALTER TABLE items DROP COLUMN IF EXISTS uiid_type;
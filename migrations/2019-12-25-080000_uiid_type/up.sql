@@ -0,0 +1,48 @@
+-- Your SQL goes here
+
+ALTER TABLE items ADD COLUMN uiid_type VARCHAR NOT NULL DEFAULT ''; -- isbn|doi|arxiv|asin
+
+-- legacy isbn-10 with valid checksum to isbn-13
+WITH isbn10 AS (
+  SELECT id, upper(uiid) AS u FROM items WHERE upper(uiid) ~ '^[0-9]{9}[0-9X]$'
+), valid AS (
+  SELECT id, '978' || left(u, 9) AS body FROM isbn10
+  WHERE (
+    SELECT SUM((11 - k) * CASE WHEN substr(u, k, 1) = 'X' THEN 10 ELSE substr(u, k, 1)::int END)
+    FROM generate_series(1, 10) k
+  ) % 11 = 0
+)
+UPDATE items SET uiid = valid.body || ((10 - (
+    SELECT SUM(substr(valid.body, k, 1)::int * CASE WHEN k % 2 = 1 THEN 1 ELSE 3 END)
+    FROM generate_series(1, 12) k
+  ) % 10) % 10)::text
+FROM valid WHERE items.id = valid.id;
+
+-- isbn-13 with valid checksum
+UPDATE items SET uiid_type = 'isbn'
+WHERE uiid ~ '^97[89][0-9]{10}$'
+  AND (
+    SELECT SUM(substr(uiid, k, 1)::int * CASE WHEN k % 2 = 1 THEN 1 ELSE 3 END)
+    FROM generate_series(1, 13) k
+  ) % 10 = 0;
+UPDATE items SET uiid = upper(uiid), uiid_type = 'asin' WHERE upper(uiid) ~ '^B0[0-9A-Z]{8}$';
+
+-- doi, lowercase w/o resolver
+UPDATE items SET uiid = d.v, uiid_type = 'doi'
+FROM (
+  SELECT id, regexp_replace(lower(trim(uiid)), '^(https?://(dx\.)?doi\.org/|doi:)\s*', '') AS v
+  FROM items
+) d
+WHERE items.id = d.id AND d.v ~ '^10\.[0-9]{4,9}/\S+$';
+
+-- arxiv, lowercase w/o version
+UPDATE items SET uiid = regexp_replace(a.v, 'v[0-9]+$', ''), uiid_type = 'arxiv'
+FROM (
+  SELECT id, regexp_replace(lower(trim(uiid)), '^(https?://arxiv\.org/abs/|arxiv:)\s*', '') AS v
+  FROM items
+) a
+WHERE items.id = a.id
+  AND a.v ~ '^([0-9]{4}\.[0-9]{4,5}|[a-z-]+(\.[a-z]{2})?/[0-9]{7})(v[0-9]+)?$';
+
+-- match per lower(uiid)
+CREATE INDEX items_uiid_idx ON items (lower(uiid));
@@ -0,0 +1,14 @@
+-- Your SQL goes here
+
+-- etc_count of items was never kept, of tags may drift, recount from etcs and tagetcs
+UPDATE items SET etc_count = (
+  SELECT COUNT(*) FROM etcs WHERE etcs.item_id = items.id
+);
+
+UPDATE tags SET etc_count = (
+  SELECT COUNT(*) FROM etcs WHERE etcs.tname = tags.tname
+) + (
+  SELECT COUNT(*) FROM tagetcs WHERE tagetcs.tname = tags.tname
+);
+
+UPDATE tags SET vote = (rut_count + item_count) * 2 + etc_count + star_count;
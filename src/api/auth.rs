@@ -2,16 +2,22 @@
 
 use actix_web::{
     error,
-    web::{self, Data, Json, Path},
+    web::{self, Data, Json, Path, Query},
     Error, HttpRequest, HttpResponse, Responder, ResponseError,
 };
 use futures::{future::result, Future};
 
+use crate::api::{respond, CursorQuery, ReqQuery};
+use crate::errors::ServiceError;
 use crate::model::msg::{AuthMsg, UserMsg};
 use crate::model::user::{
-    encode_token, AuthUser, ChangePsw, CheckUser, QueryUser, RegUser, UpdateUser,
+    encode_token, AuthUser, ChangePsw, CheckAdmin, CheckMod, CheckUser, ConfirmEmail,
+    FollowOrNot, FollowStatus, QueryFollows, QueryPermit, QueryTimelines, QueryUser,
+    RefreshSession, RegUser, ReqResetPsw, ResetPsw, RevokeSession, SendConfirmEmail,
+    UpdatePermit, UpdateUser, ACCESS_EXP,
 };
 use crate::model::Validate;
+use crate::util::mail::MailAddr;
 use crate::DbAddr;
 
 pub fn signup(
@@ -24,7 +30,7 @@ pub fn signup(
         .from_err()
         .and_then(move |_| db.send(reg).from_err())
         .and_then(|res| match res {
-            Ok(msg) => Ok(HttpResponse::Ok().json(msg)),
+            Ok(msg) => Ok(respond(msg)),
             Err(e) => Ok(e.error_response()),
         })
 }
@@ -39,21 +45,64 @@ pub fn signin(
         .from_err()
         .and_then(move |_| db.send(auth_user).from_err())
         .and_then(|res| match res {
-            Ok(user) => {
-                let token = encode_token(&user)?;
+            Ok((user, session)) => {
+                let token = encode_token(&user, &session.sid)?;
+                let auth_msg = AuthMsg {
+                    status: 200,
+                    message: "Success".to_string(),
+                    token: token,
+                    refresh: session.refresh,
+                    exp: ACCESS_EXP as i32,
+                    user: user,
+                };
+                Ok(respond(auth_msg))
+            }
+            Err(e) => Ok(e.error_response()),
+        })
+}
+
+// "/refresh" POST, new access token per refresh token
+pub fn refresh(
+    db: Data<DbAddr>,
+    rs: Json<RefreshSession>,
+) -> impl Future<Item = HttpResponse, Error = Error> {
+    db.send(rs.into_inner())
+        .from_err()
+        .and_then(|res| match res {
+            Ok((user, session)) => {
+                let token = encode_token(&user, &session.sid)?;
                 let auth_msg = AuthMsg {
                     status: 200,
                     message: "Success".to_string(),
                     token: token,
-                    exp: 5, // unit: day
+                    refresh: session.refresh,
+                    exp: ACCESS_EXP as i32,
                     user: user,
                 };
-                Ok(HttpResponse::Ok().json(auth_msg))
+                Ok(respond(auth_msg))
             }
             Err(e) => Ok(e.error_response()),
         })
 }
 
+// "/signout/{all}" POST, all: 1 to sign out everywhere
+pub fn signout(
+    db: Data<DbAddr>,
+    all: Path<u8>,
+    auth: CheckUser,
+) -> impl Future<Item = HttpResponse, Error = Error> {
+    let uname = auth.uname;
+    let sid = auth.sid;
+    let all = all.into_inner() == 1;
+
+    db.send(RevokeSession { uname, sid, all })
+        .from_err()
+        .and_then(|res| match res {
+            Ok(msg) => Ok(respond(msg)),
+            Err(err) => Ok(err.error_response()),
+        })
+}
+
 pub fn get(
     path_uname: Path<String>,
     db: Data<DbAddr>,
@@ -68,7 +117,7 @@ pub fn get(
                     message: "Success".to_string(),
                     user: user,
                 };
-                Ok(HttpResponse::Ok().json(user_msg))
+                Ok(respond(user_msg))
             }
             Err(er) => Ok(er.error_response()),
         })
@@ -76,56 +125,261 @@ pub fn get(
 
 pub fn update(
     db: Data<DbAddr>,
+    mailer: Data<MailAddr>,
     user: Json<UpdateUser>,
     auth: CheckUser,
 ) -> impl Future<Item = HttpResponse, Error = Error> {
     let up_user = user.into_inner();
+    let sid = auth.sid.clone(); // to keep the session in new token
 
     // auth.uname == user.uname
-    if auth.uname != up_user.uname {
-        panic!("No Permission"); // to have a better way!!
-    }
+    let check = if auth.uname == up_user.uname {
+        up_user.validate()
+    } else {
+        Err(ServiceError::Forbidden)
+    };
 
-    result(up_user.validate())
+    result(check)
         .from_err()
         .and_then(move |_| db.send(up_user).from_err())
-        .and_then(|res| match res {
-            Ok(u) => {
-                let token = encode_token(&u)?;
+        .and_then(move |res| match res {
+            Ok((u, mail)) => {
+                // confirm the new email, not wait
+                if let Some(m) = mail {
+                    mailer.do_send(m);
+                }
+                let token = encode_token(&u, &sid)?;
                 let auth_msg = AuthMsg {
                     status: 200,
                     message: "Success".to_string(),
                     token: token,
-                    exp: 5, // unit: day
+                    refresh: "".to_string(),
+                    exp: ACCESS_EXP as i32,
                     user: u,
                 };
-                Ok(HttpResponse::Ok().json(auth_msg))
+                Ok(respond(auth_msg))
             }
             Err(e) => Ok(e.error_response()),
         })
 }
 
+// "/permit" PUT, grant or revoke permission, admin only
+pub fn update_permit(
+    db: Data<DbAddr>,
+    up: Json<UpdatePermit>,
+    auth: CheckAdmin,
+) -> impl Future<Item = HttpResponse, Error = Error> {
+    let by = auth.0.uname;
+    let up_permit = UpdatePermit { by, ..up.into_inner() };
+
+    result(up_permit.validate())
+        .from_err()
+        .and_then(move |_| db.send(up_permit).from_err())
+        .and_then(|res| match res {
+            Ok(msg) => Ok(respond(msg)),
+            Err(e) => Ok(e.error_response()),
+        })
+}
+
+// "/permit/{uname}" GET, moderator only
+pub fn get_permit(
+    db: Data<DbAddr>,
+    path_uname: Path<String>,
+    _auth: CheckMod,
+) -> impl Future<Item = HttpResponse, Error = Error> {
+    let uname = path_uname.into_inner();
+    db.send(QueryPermit { uname })
+        .from_err()
+        .and_then(|res| match res {
+            Ok(msg) => Ok(respond(msg)),
+            Err(e) => Ok(e.error_response()),
+        })
+}
+
 pub fn change_psw(
     db: Data<DbAddr>,
     psw: Json<ChangePsw>,
     auth: CheckUser,
 ) -> impl Future<Item = HttpResponse, Error = Error> {
-    let user_psw = psw.into_inner();
+    let user_psw = ChangePsw {
+        sid: auth.sid.clone(),
+        ..psw.into_inner()
+    };
 
     // auth.uname == user.uname
-    if auth.uname != user_psw.uname {
-        panic!("No Permission"); // to have a better way!!
-    }
+    let check = if auth.uname == user_psw.uname {
+        user_psw.validate()
+    } else {
+        Err(ServiceError::Forbidden)
+    };
 
-    result(user_psw.validate())
+    result(check)
         .from_err()
         .and_then(move |_| db.send(user_psw).from_err())
         .and_then(|res| match res {
-            Ok(msg) => Ok(HttpResponse::Ok().json(msg)),
+            Ok(msg) => Ok(respond(msg)),
             Err(e) => Ok(e.error_response()),
         })
 }
 
+// "/resetpsw" POST, request a mail with token to reset password
+pub fn req_reset_psw(
+    db: Data<DbAddr>,
+    req: Json<ReqResetPsw>,
+) -> impl Future<Item = HttpResponse, Error = Error> {
+    db.send(req.into_inner())
+        .from_err()
+        .and_then(|res| match res {
+            Ok(msg) => Ok(respond(msg)),
+            Err(err) => Ok(err.error_response()),
+        })
+}
+
+// "/resetpsw" PUT, reset password per token in mail
+pub fn reset_psw(
+    db: Data<DbAddr>,
+    psw: Json<ResetPsw>,
+) -> impl Future<Item = HttpResponse, Error = Error> {
+    let reset = psw.into_inner();
+
+    result(reset.validate())
+        .from_err()
+        .and_then(move |_| db.send(reset).from_err())
+        .and_then(|res| match res {
+            Ok(msg) => Ok(respond(msg)),
+            Err(e) => Ok(e.error_response()),
+        })
+}
+
+// "/confirm" POST, send mail to confirm email
+pub fn send_confirm_email(
+    db: Data<DbAddr>,
+    auth: CheckUser,
+) -> impl Future<Item = HttpResponse, Error = Error> {
+    let uname = auth.uname;
+
+    db.send(SendConfirmEmail { uname })
+        .from_err()
+        .and_then(|res| match res {
+            Ok(msg) => Ok(respond(msg)),
+            Err(err) => Ok(err.error_response()),
+        })
+}
+
+// "/confirm/{token}" GET, via the link in mail
+pub fn confirm_email(
+    db: Data<DbAddr>,
+    tk: Path<String>,
+) -> impl Future<Item = HttpResponse, Error = Error> {
+    let token = tk.into_inner();
+
+    db.send(ConfirmEmail { token })
+        .from_err()
+        .and_then(|res| match res {
+            Ok(msg) => Ok(respond(msg)),
+            Err(err) => Ok(err.error_response()),
+        })
+}
+
+pub fn follow_or_unfollow(
+    db: Data<DbAddr>,
+    fo_info: Path<(String, u8, String)>,
+    auth: CheckUser,
+) -> impl Future<Item = HttpResponse, Error = Error> {
+    let fname = fo_info.clone().0;
+    let action: u8 = fo_info.1;
+    let note = fo_info.clone().2;
+    let uname = auth.uname;
+
+    let fo = FollowOrNot {
+        uname,
+        fname,
+        note,
+        action,
+    };
+
+    result(fo.validate())
+        .from_err()
+        .and_then(move |_| db.send(fo).from_err())
+        .and_then(|res| match res {
+            Ok(msg) => Ok(respond(msg)),
+            Err(e) => Ok(e.error_response()),
+        })
+}
+
+pub fn follow_status(
+    db: Data<DbAddr>,
+    f_info: Path<String>,
+    auth: CheckUser,
+) -> impl Future<Item = HttpResponse, Error = Error> {
+    let uname = auth.uname;
+    let fname = f_info.into_inner();
+
+    db.send(FollowStatus { uname, fname })
+        .from_err()
+        .and_then(|res| match res {
+            Ok(msg) => Ok(respond(msg)),
+            Err(err) => Ok(err.error_response()),
+        })
+}
+
+// "/follows/{per}/{uname}?page=p" GET, per: follower|following
+pub fn get_follow_list(
+    db: Data<DbAddr>,
+    pq: Query<ReqQuery>,
+    per_info: Path<(String, String)>,
+) -> impl Future<Item = HttpResponse, Error = Error> {
+    // extract Path
+    let per = per_info.0.trim();
+    let uname = per_info.clone().1;
+    // extract Query
+    let page = std::cmp::max(pq.page, 1);
+
+    let query_msg = match per {
+        "following" => QueryFollows::Following(uname, page),
+        _ => QueryFollows::Follower(uname, page),
+    };
+
+    db.send(query_msg).from_err().and_then(|res| match res {
+        Ok(msg) => Ok(respond(msg)),
+        Err(err) => Ok(err.error_response()),
+    })
+}
+
+// "/activities/{uname}?cursor=" GET
+pub fn get_activities(
+    db: Data<DbAddr>,
+    cq: Query<CursorQuery>,
+    path_uname: Path<String>,
+) -> impl Future<Item = HttpResponse, Error = Error> {
+    let uname = path_uname.into_inner();
+    let cursor = cq.into_inner().cursor.unwrap_or_default();
+
+    db.send(QueryTimelines::UserID(uname, cursor))
+        .from_err()
+        .and_then(|res| match res {
+            Ok(msg) => Ok(respond(msg)),
+            Err(err) => Ok(err.error_response()),
+        })
+}
+
+// "/feed?cursor=" GET, activity of who the user follow
+pub fn get_feed(
+    db: Data<DbAddr>,
+    cq: Query<CursorQuery>,
+    auth: CheckUser,
+) -> impl Future<Item = HttpResponse, Error = Error> {
+    let uname = auth.uname;
+    let cursor = cq.into_inner().cursor.unwrap_or_default();
+
+    db.send(QueryTimelines::Home(uname, cursor))
+        .from_err()
+        .and_then(|res| match res {
+            Ok(msg) => Ok(respond(msg)),
+            Err(err) => Ok(err.error_response()),
+        })
+}
+
 pub fn auth_token(user: CheckUser) -> HttpResponse {
     HttpResponse::Ok().json(user)
 }
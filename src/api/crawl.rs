@@ -0,0 +1,49 @@
+// api.crawl, view handler
+
+use actix_web::{
+    web::{Data, Json, Path},
+    Error, HttpResponse, ResponseError,
+};
+use futures::{future::result, Future};
+
+use crate::api::respond;
+use crate::model::crawl::{NewCrawlJob, QueryCrawlJob};
+use crate::model::user::CheckUser;
+use crate::model::{trim_url_qry, Validate};
+use crate::DbAddr;
+
+// "/crawljobs" POST, queue a url to crawl, then poll per the job id
+pub fn new_job(
+    db: Data<DbAddr>,
+    job: Json<NewCrawlJob>,
+    auth: CheckUser,
+) -> impl Future<Item = HttpResponse, Error = Error> {
+    let url = trim_url_qry(job.url.trim(), "");
+    let uname = auth.uname;
+    let new_job = NewCrawlJob { url, uname };
+
+    result(new_job.validate())
+        .from_err()
+        .and_then(move |_| db.send(new_job).from_err())
+        .and_then(|res| match res {
+            Ok(msg) => Ok(respond(msg)),
+            Err(e) => Ok(e.error_response()),
+        })
+}
+
+// "/crawljobs/{id}" GET, the status, and the item draft once done
+pub fn get_job(
+    db: Data<DbAddr>,
+    job_id: Path<String>,
+    auth: CheckUser,
+) -> impl Future<Item = HttpResponse, Error = Error> {
+    let id = job_id.into_inner();
+    let uname = auth.uname;
+
+    db.send(QueryCrawlJob { id, uname })
+        .from_err()
+        .and_then(|res| match res {
+            Ok(msg) => Ok(respond(msg)),
+            Err(err) => Ok(err.error_response()),
+        })
+}
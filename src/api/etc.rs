@@ -6,8 +6,8 @@ use actix_web::{
 };
 use futures::{future::result, Future};
 
-use crate::api::ReqQuery;
-use crate::model::etc::{Etc, PostEtc, QueryEtcs};
+use crate::api::{respond, ReqQuery, TreeQuery};
+use crate::model::etc::{DelEtc, Etc, PostEtc, QueryEtcTree, QueryEtcs, UpdateEtc};
 use crate::model::user::CheckUser;
 use crate::model::Validate;
 use crate::DbAddr;
@@ -25,11 +25,44 @@ pub fn new(
         .from_err()
         .and_then(move |_| db.send(new_etc).from_err())
         .and_then(|res| match res {
-            Ok(et) => Ok(HttpResponse::Ok().json(et)),
+            Ok(et) => Ok(respond(et)),
             Err(e) => Ok(e.error_response()),
         })
 }
 
+pub fn update(
+    db: Data<DbAddr>,
+    uetc: Json<UpdateEtc>,
+    auth: CheckUser,
+) -> impl Future<Item = HttpResponse, Error = Error> {
+    let uname = auth.uname; // pass to handler to check permission
+    let up_etc = UpdateEtc { uname, ..uetc.into_inner() };
+
+    result(up_etc.validate())
+        .from_err()
+        .and_then(move |_| db.send(up_etc).from_err())
+        .and_then(|res| match res {
+            Ok(et) => Ok(respond(et)),
+            Err(e) => Ok(e.error_response()),
+        })
+}
+
+pub fn delete(
+    db: Data<DbAddr>,
+    eid: Path<String>,
+    auth: CheckUser,
+) -> impl Future<Item = HttpResponse, Error = Error> {
+    let etc_id = eid.into_inner();
+    let uname = auth.uname; // pass to handler to check permission
+
+    db.send(DelEtc { etc_id, uname })
+        .from_err()
+        .and_then(|res| match res {
+            Ok(msg) => Ok(respond(msg)),
+            Err(err) => Ok(err.error_response()),
+        })
+}
+
 pub fn get_list(
     db: Data<DbAddr>,
     pq: Query<ReqQuery>,
@@ -39,12 +72,32 @@ pub fn get_list(
     let per = per_info.clone().0;
     let perid = per_info.clone().1;
     // extract Query
-    let page = std::cmp::max(pq.page, 1);
+    let paging = pq.paging(); // page, or cursor
+
+    db.send(QueryEtcs { per, perid, paging })
+        .from_err()
+        .and_then(|res| match res {
+            Ok(msg) => Ok(respond(msg)),
+            Err(err) => Ok(err.error_response()),
+        })
+}
+
+pub fn get_tree(
+    db: Data<DbAddr>,
+    tq: Query<TreeQuery>,
+    per_info: Path<(String, String)>,
+) -> impl Future<Item = HttpResponse, Error = Error> {
+    // extract Path
+    let per = per_info.clone().0;
+    let perid = per_info.clone().1;
+    // extract Query
+    let page = std::cmp::max(tq.page, 1);
+    let depth = tq.depth;
 
-    db.send(QueryEtcs { per, perid, page })
+    db.send(QueryEtcTree { per, perid, page, depth })
         .from_err()
         .and_then(|res| match res {
-            Ok(msg) => Ok(HttpResponse::Ok().json(msg)),
+            Ok(msg) => Ok(respond(msg)),
             Err(err) => Ok(err.error_response()),
         })
 }
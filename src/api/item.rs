@@ -4,16 +4,23 @@ use actix_web::{
     web::{self, Data, Json, Path, Query},
     Error, HttpRequest, HttpResponse, Responder, ResponseError,
 };
-use futures::{future::result, Future};
+use futures::{
+    future::{ok, result, Either},
+    Future,
+};
 
-use crate::api::ReqQuery;
+use crate::api::{respond, ReqQuery};
+use crate::errors::ServiceError;
+use crate::model::crawl::NewCrawlJob;
+use crate::model::msg::CrawlJobMsg;
 use crate::model::item::{
-    CollectItem, DelCollect, NewItem, NewStarItem, QueryCollect, QueryCollects, QueryItem,
-    QueryItems, StarItem, StarItemStatus, UpdateCollect, UpdateItem,
+    CollectItem, DecideSuggest, DelCollect, MergeItem, NewItem, NewStarItem, QueryCollect,
+    QueryCollects, QueryDupItems, QueryItem, QueryItems, QuerySuggests, ReorderCollect,
+    StarItem, StarItemStatus, UpdateCollect, UpdateItem,
 };
-use crate::model::user::CheckUser;
+use crate::model::user::{CheckEditor, CheckMod, CheckUser};
 use crate::model::Validate;
-use crate::model::{re_test_img_url, replace_sep, trim_url_qry};
+use crate::model::{normalize_uiid, re_test_img_url, trim_url_qry};
 use crate::DbAddr;
 
 pub fn new(
@@ -22,24 +29,24 @@ pub fn new(
     auth: CheckUser,
 ) -> impl Future<Item = HttpResponse, Error = Error> {
     let newItem = new_item.into_inner();
-    let uid = newItem.uiid;
-    let uiid = replace_sep(&uid, "");
-    let new_url = newItem.url;
+    let uiid_res = normalize_uiid(&newItem.uiid);
+    let new_url = newItem.url.clone();
     let url = trim_url_qry(&new_url, "");
 
-    let item_new = NewItem {
-        uiid,
-        url,
-        ..newItem
-    };
-
-    result(item_new.validate())
-        .from_err()
-        .and_then(move |_| db.send(item_new).from_err())
-        .and_then(|res| match res {
-            Ok(item) => Ok(HttpResponse::Ok().json(item)),
-            Err(e) => Ok(e.error_response()),
-        })
+    result(uiid_res.and_then(|uiid| {
+        let item_new = NewItem {
+            uiid,
+            url,
+            ..newItem
+        };
+        item_new.validate().map(|_| item_new)
+    }))
+    .from_err()
+    .and_then(move |item_new| db.send(item_new).from_err())
+    .and_then(|res| match res {
+        Ok(item) => Ok(respond(item)),
+        Err(e) => Ok(e.error_response()),
+    })
 }
 
 pub fn get(
@@ -50,7 +57,11 @@ pub fn get(
     db.send(QueryItem { item_slug })
         .from_err()
         .and_then(|res| match res {
-            Ok(msg) => Ok(HttpResponse::Ok().json(msg)),
+            // slug of a merged item, redirect to the surviving one
+            Ok(msg) if msg.status == 301 => Ok(HttpResponse::MovedPermanently()
+                .header("location", format!("/api/items/{}", msg.item.slug))
+                .json(msg)),
+            Ok(msg) => Ok(respond(msg)),
             Err(err) => Ok(err.error_response()),
         })
 }
@@ -59,12 +70,13 @@ pub fn get_list(
     db: Data<DbAddr>,
     pq: Query<ReqQuery>,
     per_info: Path<(String, String)>,
+    auth: Option<CheckUser>,
 ) -> impl Future<Item = HttpResponse, Error = Error> {
     // extract Path
     let per = per_info.0.trim();
     let perid = per_info.clone().1;
     // extract Query
-    let page = std::cmp::max(pq.page, 1);
+    let paging = pq.paging(); // page, or cursor
     let flag = pq.clone().flag;
     let kw = pq.clone().kw;
     let fr = pq.clone().fr;
@@ -84,17 +96,49 @@ pub fn get_list(
         ),
         // query per relations with  rut, tag, user
         "rut" => QueryItems::RutID(perid),
-        "tag" => QueryItems::TagID(perid),
-        "user" => QueryItems::UserID(perid, flag.parse::<i16>().unwrap_or(3), page),
-        "key" => QueryItems::KeyID(kw, fr, perid, page),
+        "tag" => QueryItems::TagID(perid, flag == "sub", paging), // flag=sub: descendants
+        "user" => QueryItems::UserID(perid, flag.parse::<i16>().unwrap_or(3), paging),
+        "key" => QueryItems::KeyID(kw, fr, perid, paging),
         _ => QueryItems::ItemID(perid),
     };
 
+    // per url, to queue a crawl if miss in db, only for a signed-in user
+    let crawl_job = match itemsPerID {
+        QueryItems::ItemUrl(ref u) => auth.map(|a| NewCrawlJob {
+            url: trim_url_qry(u.trim(), ""),
+            uname: a.uname,
+        }),
+        _ => None,
+    };
+    let db_job = db.clone();
+
     result(itemsPerID.validate())
         .from_err()
         .and_then(move |_| db.send(itemsPerID).from_err())
+        .and_then(move |res| match res {
+            Ok(ref items) if items.items.len() == 0 && crawl_job.is_some() => {
+                Either::A(queue_crawl(crawl_job.unwrap(), db_job))
+            }
+            Ok(items) => Either::B(ok(respond(items))),
+            Err(e) => Either::B(ok(e.error_response())),
+        })
+}
+
+// queue the url missed to crawl, 202 to poll the job, the item draft once done
+fn queue_crawl(
+    job: NewCrawlJob,
+    db: Data<DbAddr>,
+) -> impl Future<Item = HttpResponse, Error = Error> {
+    result(job.validate())
+        .from_err()
+        .and_then(move |_| db.send(job).from_err())
         .and_then(|res| match res {
-            Ok(items) => Ok(HttpResponse::Ok().json(items)),
+            Ok(msg) => Ok(HttpResponse::Accepted()
+                .header("location", format!("/api/crawljobs/{}", msg.job.id))
+                .json(CrawlJobMsg {
+                    status: 202,
+                    ..msg
+                })),
             Err(e) => Ok(e.error_response()),
         })
 }
@@ -102,28 +146,28 @@ pub fn get_list(
 pub fn update(
     db: Data<DbAddr>,
     up_item: Json<UpdateItem>,
-    auth: CheckUser,
+    auth: CheckEditor,
 ) -> impl Future<Item = HttpResponse, Error = Error> {
     let upItem = up_item.into_inner();
 
-    let uid = upItem.uiid;
-    let uiid = replace_sep(&uid, "");
-    let up_url = upItem.url;
+    let uiid_res = normalize_uiid(&upItem.uiid);
+    let up_url = upItem.url.clone();
     let url = trim_url_qry(&up_url, "");
 
-    let item_up = UpdateItem {
-        uiid,
-        url,
-        ..upItem
-    };
-
-    result(item_up.validate())
-        .from_err()
-        .and_then(move |_| db.send(item_up).from_err())
-        .and_then(|res| match res {
-            Ok(item) => Ok(HttpResponse::Ok().json(item)),
-            Err(e) => Ok(e.error_response()),
-        })
+    result(uiid_res.and_then(|uiid| {
+        let item_up = UpdateItem {
+            uiid,
+            url,
+            ..upItem
+        };
+        item_up.validate().map(|_| item_up)
+    }))
+    .from_err()
+    .and_then(move |item_up| db.send(item_up).from_err())
+    .and_then(|res| match res {
+        Ok(item) => Ok(respond(item)),
+        Err(e) => Ok(e.error_response()),
+    })
 }
 
 pub fn collect_item(
@@ -132,11 +176,12 @@ pub fn collect_item(
     auth: CheckUser,
 ) -> impl Future<Item = HttpResponse, Error = Error> {
     // todo some check of input
+    let collect = CollectItem{ uname: auth.uname, ..c_item.into_inner() };
 
-    db.send(c_item.into_inner())
+    db.send(collect)
         .from_err()
         .and_then(|res| match res {
-            Ok(item) => Ok(HttpResponse::Ok().json(item)),
+            Ok(item) => Ok(respond(item)),
             Err(err) => Ok(err.error_response()),
         })
 }
@@ -145,22 +190,23 @@ pub fn get_collect_list(
     db: Data<DbAddr>,
     pq: Query<ReqQuery>,
     per_info: Path<(String, String)>,
+    auth: Option<CheckUser>,
 ) -> impl Future<Item = HttpResponse, Error = Error> {
     // extract Path
     let per = per_info.0.trim();
     let perid = per_info.clone().1;
     // extract Query
-    let page = std::cmp::max(pq.page, 1);
+    let paging = pq.paging(); // page, or cursor
 
     let collectIDs = match per {
-        "item" => QueryCollects::ItemID(perid, page),
+        "item" => QueryCollects::ItemID(perid, paging),
         "rut" => QueryCollects::RutID(perid),
-        "user" => QueryCollects::UserID(perid, page),
+        "user" => QueryCollects::UserID(perid, paging),
         _ => QueryCollects::RutID(perid),
     };
 
     db.send(collectIDs).from_err().and_then(|res| match res {
-        Ok(msg) => Ok(HttpResponse::Ok().json(msg)),
+        Ok(msg) => Ok(respond(msg)),
         Err(err) => Ok(err.error_response()),
     })
 }
@@ -174,7 +220,7 @@ pub fn get_collect(
     db.send(QueryCollect { collect_id, action })
         .from_err()
         .and_then(|res| match res {
-            Ok(msg) => Ok(HttpResponse::Ok().json(msg)),
+            Ok(msg) => Ok(respond(msg)),
             Err(err) => Ok(err.error_response()),
         })
 }
@@ -192,7 +238,7 @@ pub fn del_collect(
     db.send(DelCollect { collect_id, uname })
         .from_err()
         .and_then(|res| match res {
-            Ok(msg) => Ok(HttpResponse::Ok().json(msg)),
+            Ok(msg) => Ok(respond(msg)),
             Err(err) => Ok(err.error_response()),
         })
 }
@@ -202,17 +248,40 @@ pub fn update_collect(
     up_collect: Json<UpdateCollect>,
     auth: CheckUser,
 ) -> impl Future<Item = HttpResponse, Error = Error> {
-    // need to check the auth_uname == collect_uname, on frontend??
-    // check id eque
+    let uname = auth.uname; // pass to handler to check permission
+    let collect_up = UpdateCollect { uname, ..up_collect.into_inner() };
 
-    db.send(up_collect.into_inner())
+    db.send(collect_up)
         .from_err()
         .and_then(|res| match res {
-            Ok(cmsg) => Ok(HttpResponse::Ok().json(cmsg)),
+            Ok(cmsg) => Ok(respond(cmsg)),
             Err(err) => Ok(err.error_response()),
         })
 }
 
+pub fn reorder_collect(
+    db: Data<DbAddr>,
+    ro: Json<ReorderCollect>,
+    rutid: Path<String>,
+    auth: CheckUser,
+) -> impl Future<Item = HttpResponse, Error = Error> {
+    let rut_id = rutid.into_inner();
+    let uname = auth.uname; // pass to handler to check permission
+    let re_order = ReorderCollect {
+        rut_id,
+        uname,
+        ..ro.into_inner()
+    };
+
+    result(re_order.validate())
+        .from_err()
+        .and_then(move |_| db.send(re_order).from_err())
+        .and_then(|res| match res {
+            Ok(msg) => Ok(respond(msg)),
+            Err(e) => Ok(e.error_response()),
+        })
+}
+
 pub fn star_item(
     db: Data<DbAddr>,
     auth: CheckUser,
@@ -233,7 +302,7 @@ pub fn star_item(
     })
     .from_err()
     .and_then(|res| match res {
-        Ok(msg) => Ok(HttpResponse::Ok().json(msg)),
+        Ok(msg) => Ok(respond(msg)),
         Err(err) => Ok(err.error_response()),
     })
 }
@@ -249,7 +318,80 @@ pub fn star_status(
     db.send(StarItemStatus { uname, item_id })
         .from_err()
         .and_then(|res| match res {
-            Ok(msg) => Ok(HttpResponse::Ok().json(msg)),
+            Ok(msg) => Ok(respond(msg)),
+            Err(err) => Ok(err.error_response()),
+        })
+}
+
+// "/itemmerge" POST, merge a duplicate item into another, moderator only
+pub fn merge(
+    db: Data<DbAddr>,
+    mg: Json<MergeItem>,
+    auth: CheckMod,
+) -> impl Future<Item = HttpResponse, Error = Error> {
+    let uname = auth.0.uname;
+    let merge_item = MergeItem { uname, ..mg.into_inner() };
+
+    result(merge_item.validate())
+        .from_err()
+        .and_then(move |_| db.send(merge_item).from_err())
+        .and_then(|res| match res {
+            Ok(msg) => Ok(respond(msg)),
+            Err(e) => Ok(e.error_response()),
+        })
+}
+
+// "/itemdups?page=&size=" GET, report likely duplicates, moderator only
+pub fn get_dups(
+    db: Data<DbAddr>,
+    pq: Query<ReqQuery>,
+    _auth: CheckMod,
+) -> impl Future<Item = HttpResponse, Error = Error> {
+    let paging = pq.paging();
+
+    db.send(QueryDupItems { paging })
+        .from_err()
+        .and_then(|res| match res {
+            Ok(msg) => Ok(respond(msg)),
+            Err(err) => Ok(err.error_response()),
+        })
+}
+
+// "/suggests?flag=&page=&size=" GET, metadata suggests per link check, moderator only
+pub fn get_suggests(
+    db: Data<DbAddr>,
+    pq: Query<ReqQuery>,
+    _auth: CheckMod,
+) -> impl Future<Item = HttpResponse, Error = Error> {
+    let paging = pq.paging();
+    let flag = pq.clone().flag;
+    let status = match flag.trim() {
+        "approved" | "rejected" => flag.trim().to_owned(),
+        _ => "pending".to_owned(),
+    };
+
+    db.send(QuerySuggests { status, paging })
+        .from_err()
+        .and_then(|res| match res {
+            Ok(msg) => Ok(respond(msg)),
+            Err(err) => Ok(err.error_response()),
+        })
+}
+
+// "/suggests/{id}/{action}" PUT, approve 1 or reject 0, moderator only
+pub fn decide_suggest(
+    db: Data<DbAddr>,
+    p_info: Path<(String, u8)>,
+    auth: CheckMod,
+) -> impl Future<Item = HttpResponse, Error = Error> {
+    let id = p_info.0.clone();
+    let action = p_info.1;
+    let uname = auth.0.uname;
+
+    db.send(DecideSuggest { id, action, uname })
+        .from_err()
+        .and_then(|res| match res {
+            Ok(msg) => Ok(respond(msg)),
             Err(err) => Ok(err.error_response()),
         })
 }
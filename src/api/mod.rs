@@ -8,16 +8,56 @@
 // actor: db, typed model,  msg handler
 
 pub mod auth;
+pub mod crawl;
 pub mod etc;
 pub mod item;
 pub mod rut;
+pub mod search;
 pub mod tag;
 
-// for extract typed request Query info: /path?page=&flag=&kw=&fr=
+use actix_web::{http::StatusCode, HttpResponse};
+
+use crate::model::msg::StatusMsg;
+use crate::model::{Paging, TREE_DEPTH};
+
+// respond with the http status same as the status in msg
+pub fn respond<T: StatusMsg>(msg: T) -> HttpResponse {
+    let status = StatusCode::from_u16(msg.status() as u16).unwrap_or(StatusCode::OK);
+    HttpResponse::build(status).json(msg)
+}
+
+// for extract typed request Query info: /path?page=&flag=&kw=&fr=&cursor=&size=
 #[derive(Deserialize, Clone)]
 pub struct ReqQuery {
     page: i32,
     flag: String,
     kw: String, // keyword  Option<String>?
     fr: String, // from user|tag..
+    cursor: Option<String>, // keyset paging, over page if any
+    size: Option<i32>,      // page size
+}
+
+impl ReqQuery {
+    pub fn paging(&self) -> Paging {
+        Paging::new(self.page, self.cursor.clone(), self.size)
+    }
+}
+
+// for extract Query info of cursor paging: /path?cursor=
+#[derive(Deserialize, Clone)]
+pub struct CursorQuery {
+    cursor: Option<String>,
+}
+
+// for extract Query info of reply tree: /path?page=&depth=, both optional
+#[derive(Deserialize, Clone)]
+pub struct TreeQuery {
+    #[serde(default)]
+    page: i32,
+    #[serde(default = "tree_depth")]
+    depth: i32,
+}
+
+fn tree_depth() -> i32 {
+    TREE_DEPTH
 }
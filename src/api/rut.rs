@@ -6,9 +6,9 @@ use actix_web::{
 };
 use futures::{future::result, Future};
 
-use crate::api::ReqQuery;
+use crate::api::{respond, ReqQuery};
 use crate::model::{
-    rut::{CreateRut, QueryRut, QueryRuts, StarOrRut, StarRutStatus, UpdateRut},
+    rut::{CreateRut, DelRut, QueryRut, QueryRuts, StarOrRut, StarRutStatus, UpdateRut},
     user::CheckUser,
     Validate,
 };
@@ -20,13 +20,13 @@ pub fn new(
     rut: Json<CreateRut>,
     auth: CheckUser,
 ) -> impl Future<Item = HttpResponse, Error = Error> {
-    let new_rut = rut.into_inner();
+    let new_rut = CreateRut{ uname: auth.uname, ..rut.into_inner() };
 
     result(new_rut.validate())
         .from_err()
         .and_then(move |_| db.send(new_rut).from_err())
         .and_then(|res| match res {
-            Ok(r) => Ok(HttpResponse::Ok().json(r)),
+            Ok(r) => Ok(respond(r)),
             Err(e) => Ok(e.error_response()),
         })
 }
@@ -40,12 +40,12 @@ pub fn get(
     db.send(QueryRut { rut_slug })
         .from_err()
         .and_then(|res| match res {
-            Ok(msg) => Ok(HttpResponse::Ok().json(msg)),
+            Ok(msg) => Ok(respond(msg)),
             Err(err) => Ok(err.error_response()),
         })
 }
 
-// "/ruts/{per}/{perid}?page=p&flag=create|star&kw= fr=" GET
+// "/ruts/{per}/{perid}?page=p&flag=create|star&kw= fr=&cursor=&size=" GET
 pub fn get_list(
     db: Data<DbAddr>,
     pq: Query<ReqQuery>,
@@ -55,21 +55,21 @@ pub fn get_list(
     let per = per_info.0.trim();
     let perid = per_info.clone().1;
     // extract Query
-    let page = std::cmp::max(pq.page, 1);
+    let paging = pq.paging(); // page, or cursor
     let flag = pq.clone().flag;
     let kw = pq.clone().kw;
     let fr = pq.clone().fr;
 
     let query_msg = match per {
-        "item" => QueryRuts::ItemID(perid, page),
-        "tag" => QueryRuts::TagID(perid, page),
-        "user" => QueryRuts::UserID(perid, flag, page), // flag=create|star
-        "key" => QueryRuts::KeyID(kw, fr, perid, page), // &kw=&fr=tag|user|item
+        "item" => QueryRuts::ItemID(perid, paging),
+        "tag" => QueryRuts::TagID(perid, flag == "sub", paging), // flag=sub: descendants
+        "user" => QueryRuts::UserID(perid, flag, paging), // flag=create|star
+        "key" => QueryRuts::KeyID(kw, fr, perid, paging), // &kw=&fr=tag|user|item
         _ => QueryRuts::Index(String::from("index")),
     };
 
     db.send(query_msg).from_err().and_then(|res| match res {
-        Ok(msg) => Ok(HttpResponse::Ok().json(msg)),
+        Ok(msg) => Ok(respond(msg)),
         Err(err) => Ok(err.error_response()),
     })
 }
@@ -85,7 +85,7 @@ pub fn update(
         .from_err()
         .and_then(move |_| db.send(up_rut).from_err())
         .and_then(|res| match res {
-            Ok(r) => Ok(HttpResponse::Ok().json(r)),
+            Ok(r) => Ok(respond(r)),
             Err(e) => Ok(e.error_response()),
         })
 }
@@ -108,7 +108,7 @@ pub fn star_or_unstar(
     })
     .from_err()
     .and_then(|res| match res {
-        Ok(msg) => Ok(HttpResponse::Ok().json(msg)),
+        Ok(msg) => Ok(respond(msg)),
         Err(err) => Ok(err.error_response()),
     })
 }
@@ -124,10 +124,24 @@ pub fn star_status(
     db.send(StarRutStatus { uname, rut_id })
         .from_err()
         .and_then(|res| match res {
-            Ok(msg) => Ok(HttpResponse::Ok().json(msg)),
+            Ok(msg) => Ok(respond(msg)),
             Err(err) => Ok(err.error_response()),
         })
 }
 
-// todo
-// pub fn delete() {}
+// "/ruts/{slug}" DELETE
+pub fn delete(
+    db: Data<DbAddr>,
+    r_slug: Path<String>,
+    auth: CheckUser,
+) -> impl Future<Item = HttpResponse, Error = Error> {
+    let rut_slug = r_slug.into_inner();
+    let uname = auth.uname; // pass to handler to check permission
+
+    db.send(DelRut { rut_slug, uname })
+        .from_err()
+        .and_then(|res| match res {
+            Ok(msg) => Ok(respond(msg)),
+            Err(err) => Ok(err.error_response()),
+        })
+}
@@ -0,0 +1,41 @@
+// api.search, view handler
+
+use actix_web::{
+    web::{Data, Query},
+    Error, HttpResponse, ResponseError,
+};
+use futures::{future::result, Future};
+
+use crate::api::respond;
+use crate::model::search::Search;
+use crate::model::Validate;
+use crate::DbAddr;
+
+// for extract Query info: /search?kw=&ty=&page=
+#[derive(Deserialize, Clone)]
+pub struct SearchQuery {
+    kw: String,
+    ty: Option<String>, // rut|item|tag|etc
+    page: Option<i32>,
+}
+
+// "/search?kw=&ty=&page=" GET
+pub fn search(
+    db: Data<DbAddr>,
+    sq: Query<SearchQuery>,
+) -> impl Future<Item = HttpResponse, Error = Error> {
+    let q = sq.into_inner();
+    let search = Search {
+        kw: q.kw,
+        ty: q.ty.unwrap_or_default(),
+        page: std::cmp::max(q.page.unwrap_or(1), 1),
+    };
+
+    result(search.validate())
+        .from_err()
+        .and_then(move |_| db.send(search).from_err())
+        .and_then(|res| match res {
+            Ok(msg) => Ok(respond(msg)),
+            Err(e) => Ok(e.error_response()),
+        })
+}
@@ -6,12 +6,12 @@ use actix_web::{
 };
 use futures::{future::result, Future};
 
-use crate::api::ReqQuery;
+use crate::api::{respond, ReqQuery};
 use crate::model::tag::{
-    CheckTag, QueryTags, RutTag, StarOrTag, 
-    StarTagStatus, Tag, TagAny, TagRut, UpdateTag,
+    CheckTag, MergeTag, QueryTagAliases, QueryTagTree, QueryTags, RutTag, StarOrTag, 
+    StarTagStatus, Tag, TagAny, TagRut, UpdateTag, UpdateTagAlias,
 };
-use crate::model::user::CheckUser;
+use crate::model::user::{CheckEditor, CheckMod, CheckUser};
 use crate::model::{replace_sep_tag, Validate, TAG_LEN};
 use crate::DbAddr;
 
@@ -29,7 +29,7 @@ pub fn new(
         .from_err()
         .and_then(move |_| db.send(tag).from_err())
         .and_then(|res| match res {
-            Ok(t) => Ok(HttpResponse::Ok().json(t)),
+            Ok(t) => Ok(respond(t)),
             Err(e) => Ok(e.error_response()),
         })
 }
@@ -43,7 +43,11 @@ pub fn get(db: Data<DbAddr>, tg: Path<String>) -> impl Future<Item = HttpRespons
     db.send(CheckTag { tname, action })
         .from_err()
         .and_then(|res| match res {
-            Ok(tag) => Ok(HttpResponse::Ok().json(tag)),
+            // alias or merged tag, redirect to the canonical one
+            Ok(tag) if tag.status == 301 => Ok(HttpResponse::MovedPermanently()
+                .header("location", format!("/api/tags/{}", tag.tag.tname))
+                .json(tag)),
+            Ok(tag) => Ok(respond(tag)),
             Err(err) => Ok(err.error_response()),
         })
 }
@@ -65,15 +69,31 @@ pub fn get_list(
     };
 
     db.send(tg_msg).from_err().and_then(|res| match res {
-        Ok(msg) => Ok(HttpResponse::Ok().json(msg)),
+        Ok(msg) => Ok(respond(msg)),
         Err(err) => Ok(err.error_response()),
     })
 }
 
+// "/tagtree/{per}/{tname}" GET, per: ancestor|subtree
+pub fn get_tree(
+    db: Data<DbAddr>,
+    per_info: Path<(String, String)>,
+) -> impl Future<Item = HttpResponse, Error = Error> {
+    let per = per_info.clone().0;
+    let tname = per_info.clone().1;
+
+    db.send(QueryTagTree { tname, per })
+        .from_err()
+        .and_then(|res| match res {
+            Ok(msg) => Ok(respond(msg)),
+            Err(err) => Ok(err.error_response()),
+        })
+}
+
 pub fn update(
     db: Data<DbAddr>,
     tg: Json<UpdateTag>,
-    auth: CheckUser,
+    auth: CheckEditor,
 ) -> impl Future<Item = HttpResponse, Error = Error> {
     let tag = tg.into_inner();
     // todo some check
@@ -89,7 +109,7 @@ pub fn update(
         .from_err()
         .and_then(move |_| db.send(up_tag).from_err())
         .and_then(|res| match res {
-            Ok(t) => Ok(HttpResponse::Ok().json(t)),
+            Ok(t) => Ok(respond(t)),
             Err(e) => Ok(e.error_response()),
         })
 }
@@ -118,7 +138,7 @@ pub fn tag_rut(
         .from_err()
         .and_then(move |_| db.send(rut_tags).from_err())
         .and_then(|res| match res {
-            Ok(msg) => Ok(HttpResponse::Ok().json(msg)),
+            Ok(msg) => Ok(respond(msg)),
             Err(e) => Ok(e.error_response()),
         })
 }
@@ -146,7 +166,7 @@ pub fn tag_any(
         .from_err()
         .and_then(move |_| db.send(any_tags).from_err())
         .and_then(|res| match res {
-            Ok(msg) => Ok(HttpResponse::Ok().json(msg)),
+            Ok(msg) => Ok(respond(msg)),
             Err(e) => Ok(e.error_response()),
         })
 }
@@ -168,7 +188,7 @@ pub fn star_or_unstar(
     })
     .from_err()
     .and_then(|res| match res {
-        Ok(msg) => Ok(HttpResponse::Ok().json(msg)),
+        Ok(msg) => Ok(respond(msg)),
         Err(err) => Ok(err.error_response()),
     })
 }
@@ -184,7 +204,64 @@ pub fn star_status(
     db.send(StarTagStatus { uname, tname })
         .from_err()
         .and_then(|res| match res {
-            Ok(msg) => Ok(HttpResponse::Ok().json(msg)),
+            Ok(msg) => Ok(respond(msg)),
+            Err(err) => Ok(err.error_response()),
+        })
+}
+
+// "/tagalias" PUT, set or remove an alias, moderator only
+pub fn update_alias(
+    db: Data<DbAddr>,
+    ta: Json<UpdateTagAlias>,
+    auth: CheckMod,
+) -> impl Future<Item = HttpResponse, Error = Error> {
+    let ta = ta.into_inner();
+    let alias = replace_sep_tag(ta.alias.trim(), "-");
+    let tname = replace_sep_tag(ta.tname.trim(), "-");
+    let uname = auth.0.uname;
+    let up_alias = UpdateTagAlias { alias, tname, uname, ..ta };
+
+    result(up_alias.validate())
+        .from_err()
+        .and_then(move |_| db.send(up_alias).from_err())
+        .and_then(|res| match res {
+            Ok(msg) => Ok(respond(msg)),
+            Err(e) => Ok(e.error_response()),
+        })
+}
+
+// "/tagalias/{tname}" GET
+pub fn get_aliases(
+    db: Data<DbAddr>,
+    tg: Path<String>,
+) -> impl Future<Item = HttpResponse, Error = Error> {
+    let tname = tg.into_inner();
+
+    db.send(QueryTagAliases { tname })
+        .from_err()
+        .and_then(|res| match res {
+            Ok(msg) => Ok(respond(msg)),
             Err(err) => Ok(err.error_response()),
         })
 }
+
+// "/tagmerge" POST, merge a tag into another, moderator only
+pub fn merge(
+    db: Data<DbAddr>,
+    mg: Json<MergeTag>,
+    auth: CheckMod,
+) -> impl Future<Item = HttpResponse, Error = Error> {
+    let mg = mg.into_inner();
+    let from = replace_sep_tag(mg.from.trim(), "-");
+    let to = replace_sep_tag(mg.to.trim(), "-");
+    let uname = auth.0.uname;
+    let merge_tag = MergeTag { from, to, uname };
+
+    result(merge_tag.validate())
+        .from_err()
+        .and_then(move |_| db.send(merge_tag).from_err())
+        .and_then(|res| match res {
+            Ok(msg) => Ok(respond(msg)),
+            Err(e) => Ok(e.error_response()),
+        })
+}
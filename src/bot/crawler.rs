@@ -0,0 +1,135 @@
+// crawl job queue runner, poll due jobs from db, crawle via Spider
+
+use actix::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+use crate::bot::{SpiderAddr, SpiderItem};
+use crate::errors::ServiceError;
+use crate::model::crawl::{
+    ClaimCrawlJobs, CrawlJob, FinishCrawlJob, ResetCrawlJobs, CRAWL_CONCURRENCY, CRAWL_POLL,
+    POLITE_DELAY,
+};
+use crate::DbAddr;
+
+pub struct Crawler {
+    db: DbAddr,
+    spider: SpiderAddr,
+    running: HashSet<String>,        // domains of running jobs
+    delay: HashMap<String, Instant>, // domain -> not before, politeness
+    claiming: bool,                  // a claim in flight
+}
+
+impl Crawler {
+    pub fn new(db: DbAddr, spider: SpiderAddr) -> Self {
+        Crawler {
+            db,
+            spider,
+            running: HashSet::new(),
+            delay: HashMap::new(),
+            claiming: false,
+        }
+    }
+
+    // claim due jobs as many as free slots, skip the busy domains
+    fn poll(&mut self, ctx: &mut Context<Self>) {
+        let free = CRAWL_CONCURRENCY.saturating_sub(self.running.len());
+        if free == 0 || self.claiming {
+            return;
+        }
+        let now = Instant::now();
+        self.delay.retain(|_, until| *until > now);
+        let skip: Vec<String> = self
+            .running
+            .iter()
+            .chain(self.delay.keys())
+            .cloned()
+            .collect();
+
+        self.claiming = true;
+        let claim = self
+            .db
+            .send(ClaimCrawlJobs { limit: free, skip })
+            .into_actor(self)
+            .then(|res, act, ctx| {
+                act.claiming = false;
+                match res {
+                    Ok(Ok(jobs)) => {
+                        for job in jobs {
+                            act.run_job(job, ctx);
+                        }
+                    }
+                    Ok(Err(e)) => warn!("crawl claim: {}", e),
+                    Err(e) => warn!("crawl claim: {}", e),
+                }
+                fut::ok(())
+            });
+        ctx.spawn(claim);
+    }
+
+    fn run_job(&mut self, job: CrawlJob, ctx: &mut Context<Self>) {
+        let CrawlJob { id, url, domain, .. } = job;
+        self.running.insert(domain.clone());
+
+        let run = self
+            .spider
+            .send(SpiderItem { url })
+            .into_actor(self)
+            .then(move |res, act, _| {
+                act.running.remove(&domain);
+                act.delay
+                    .insert(domain, Instant::now() + Duration::from_secs(POLITE_DELAY));
+
+                let res = res
+                    .unwrap_or_else(|_| Err(ServiceError::InternalServerError("Mailbox".into())));
+                let finish = match res {
+                    Ok(draft) => FinishCrawlJob {
+                        id,
+                        draft: Some(draft),
+                        error: "".to_owned(),
+                        retry: false,
+                    },
+                    Err(e) => FinishCrawlJob {
+                        id,
+                        draft: None,
+                        retry: is_transient(&e),
+                        error: e.to_string(),
+                    },
+                };
+                act.db.do_send(finish);
+                fut::ok(())
+            });
+        ctx.spawn(run);
+    }
+}
+
+// worth a retry: per the fetch error, or the mailbox
+fn is_transient(e: &ServiceError) -> bool {
+    match e {
+        ServiceError::Fetch(f) => f.is_transient(),
+        ServiceError::InternalServerError(_) => true,
+        _ => false,
+    }
+}
+
+impl Actor for Crawler {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        // re-queue the jobs left running by a last shutdown, then poll
+        let reset = self
+            .db
+            .send(ResetCrawlJobs)
+            .into_actor(self)
+            .then(|res, _, ctx| {
+                match res {
+                    Ok(Ok(n)) => info!("crawl jobs re-queued: {}", n),
+                    Ok(Err(e)) => warn!("crawl reset: {}", e),
+                    Err(e) => warn!("crawl reset: {}", e),
+                }
+                ctx.run_interval(Duration::from_secs(CRAWL_POLL), |act, ctx| act.poll(ctx));
+                fut::ok(())
+            });
+        ctx.wait(reset);
+    }
+}
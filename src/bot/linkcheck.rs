@@ -0,0 +1,97 @@
+// periodic link check, load stale items from db, check one per tick via Spider
+
+use actix::prelude::*;
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use crate::bot::{CheckLink, SpiderAddr};
+use crate::model::item::{
+    Item, QueryLinkCheck, SaveLinkCheck, LINK_CHECK_BATCH, LINK_CHECK_TICK,
+};
+use crate::DbAddr;
+
+pub struct LinkChecker {
+    db: DbAddr,
+    spider: SpiderAddr,
+    queue: VecDeque<Item>, // loaded, to check
+    loading: bool,         // a load in flight
+    busy: bool,            // a check in flight
+}
+
+impl LinkChecker {
+    pub fn new(db: DbAddr, spider: SpiderAddr) -> Self {
+        LinkChecker {
+            db,
+            spider,
+            queue: VecDeque::new(),
+            loading: false,
+            busy: false,
+        }
+    }
+
+    // check the next item, or load a batch if none
+    fn tick(&mut self, ctx: &mut Context<Self>) {
+        if self.busy || self.loading {
+            return;
+        }
+        match self.queue.pop_front() {
+            Some(item) => self.check(item, ctx),
+            None => self.load(ctx),
+        }
+    }
+
+    fn load(&mut self, ctx: &mut Context<Self>) {
+        self.loading = true;
+        let load = self
+            .db
+            .send(QueryLinkCheck {
+                limit: LINK_CHECK_BATCH,
+            })
+            .into_actor(self)
+            .then(|res, act, _| {
+                act.loading = false;
+                match res {
+                    Ok(Ok(items)) => act.queue.extend(items),
+                    Ok(Err(e)) => warn!("link check load: {}", e),
+                    Err(e) => warn!("link check load: {}", e),
+                }
+                fut::ok(())
+            });
+        ctx.spawn(load);
+    }
+
+    fn check(&mut self, item: Item, ctx: &mut Context<Self>) {
+        self.busy = true;
+        let item_id = item.id;
+        let check = self
+            .spider
+            .send(CheckLink {
+                url: item.url,
+                cover: item.cover,
+            })
+            .into_actor(self)
+            .then(move |res, act, _| {
+                act.busy = false;
+                match res {
+                    Ok(Ok(r)) => act.db.do_send(SaveLinkCheck {
+                        item_id,
+                        url_status: r.url_status,
+                        cover_status: r.cover_status,
+                        draft: r.draft,
+                    }),
+                    Ok(Err(e)) => warn!("link check {}: {}", item_id, e),
+                    Err(e) => warn!("link check {}: {}", item_id, e),
+                }
+                fut::ok(())
+            });
+        ctx.spawn(check);
+    }
+}
+
+impl Actor for LinkChecker {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        ctx.run_interval(Duration::from_secs(LINK_CHECK_TICK), |act, ctx| act.tick(ctx));
+    }
+}
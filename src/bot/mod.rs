@@ -1,11 +1,243 @@
-// a simple page crawle
+// a simple page crawle, run in Spider actor, off the Dba
 
-use regex::Regex;
-use reqwest;
-use scraper::{Html, Selector};
+use actix::{Actor, Addr, Handler, Message, SyncArbiter, SyncContext};
+use reqwest::header::{HeaderMap, HeaderValue, CONTENT_TYPE, USER_AGENT};
+use reqwest::{Client, RedirectPolicy, Url};
+use scraper::Html;
+use std::io::{Error as IoError, ErrorKind as IoErrorKind, Read};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, ToSocketAddrs};
+use std::time::Duration;
 
+use crate::errors::{FetchError, ServiceError};
 use crate::model::item::NewItem;
-use crate::model::{re_test_img_url, replace_sep, trim_url_qry};
+use crate::model::re_test_url;
+
+pub mod crawler;
+pub mod linkcheck;
+pub mod parser;
+pub mod robots;
+
+use parser::PARSERS;
+use robots::robots_allowed;
+
+// some const for crawle
+pub const SPIDER_NUM: usize = 4; // threads of Spider
+pub const FETCH_TIMEOUT: u64 = 10; // seconds, whole request
+pub const CONNECT_TIMEOUT: u64 = 5; // seconds
+pub const MAX_REDIRECTS: usize = 5;
+pub const MAX_PAGE_SIZE: u64 = 2 * 1024 * 1024; // bytes
+pub const BOT_NAME: &str = "rutbot"; // to match in robots.txt
+pub const BOT_UA: &str = "rutbot/0.1";
+
+// crawler executor actor, blocking io per thread
+pub struct Spider(pub Client);
+
+impl Actor for Spider {
+    type Context = SyncContext<Self>;
+}
+
+pub type SpiderAddr = Addr<Spider>;
+
+pub fn new_client() -> Result<Client, ServiceError> {
+    build_client(Duration::from_secs(FETCH_TIMEOUT), true)
+}
+
+// guard: refuse the non-public host on each redirect hop
+fn build_client(timeout: Duration, guard: bool) -> Result<Client, ServiceError> {
+    let mut headers = HeaderMap::new();
+    headers.insert(USER_AGENT, HeaderValue::from_static(BOT_UA));
+    let policy = RedirectPolicy::custom(move |attempt| {
+        if attempt.previous().len() > MAX_REDIRECTS {
+            attempt.too_many_redirects()
+        } else if guard && !public_url(attempt.url()) {
+            attempt.error("Blocked Host")
+        } else {
+            attempt.follow()
+        }
+    });
+    let client = Client::builder()
+        .default_headers(headers)
+        .timeout(timeout)
+        .connect_timeout(Duration::from_secs(CONNECT_TIMEOUT))
+        .redirect(policy)
+        .build()?;
+
+    Ok(client)
+}
+
+pub fn init_spider() -> Result<SpiderAddr, ServiceError> {
+    let client = new_client()?;
+
+    Ok(SyncArbiter::start(SPIDER_NUM, move || Spider(client.clone())))
+}
+
+// check url and its host before fetch, no loopback, private, link-local...
+pub fn check_url(url: &str) -> Result<(), ServiceError> {
+    if !re_test_url(url) {
+        return Err(ServiceError::BadRequest("Invalid URL".into()));
+    }
+    let u = Url::parse(url).map_err(|_| ServiceError::BadRequest("Invalid URL".into()))?;
+    if public_url(&u) {
+        Ok(())
+    } else {
+        Err(ServiceError::BadRequest("Blocked Host".into()))
+    }
+}
+
+// all addresses the host resolves to are public, false if not resolved
+fn public_url(u: &Url) -> bool {
+    let host = u.host_str().unwrap_or("").trim_start_matches('[').trim_end_matches(']');
+    let port = u.port_or_known_default().unwrap_or(80);
+    match (host, port).to_socket_addrs() {
+        Ok(addrs) => {
+            let ips: Vec<IpAddr> = addrs.map(|a| a.ip()).collect();
+            ips.len() > 0 && ips.iter().all(public_ip)
+        }
+        Err(_) => false,
+    }
+}
+
+pub fn public_ip(ip: &IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => public_ipv4(v4),
+        IpAddr::V6(v6) => public_ipv6(v6),
+    }
+}
+
+fn public_ipv4(ip: &Ipv4Addr) -> bool {
+    let o = ip.octets();
+    !(ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_broadcast()
+        || ip.is_unspecified()
+        || ip.is_multicast()
+        || ip.is_documentation()
+        || o[0] == 0 // this network
+        || (o[0] == 100 && o[1] & 0xc0 == 64) // shared, 100.64/10
+        || o[0] >= 240) // reserved
+}
+
+fn public_ipv6(ip: &Ipv6Addr) -> bool {
+    let seg = ip.segments();
+    // v4 mapped or compatible, per the v4 rules
+    if seg[..5].iter().all(|s| *s == 0) && (seg[5] == 0xffff || seg[5] == 0) && !ip.is_loopback() {
+        if let Some(v4) = ip.to_ipv4() {
+            return public_ipv4(&v4);
+        }
+    }
+    !(ip.is_loopback()
+        || ip.is_unspecified()
+        || ip.is_multicast()
+        || seg[0] & 0xfe00 == 0xfc00 // unique local, fc00::/7
+        || seg[0] & 0xffc0 == 0xfe80) // link-local, fe80::/10
+}
+
+// reading a fetched body, timeout or network
+fn read_error(error: IoError) -> ServiceError {
+    match error.kind() {
+        IoErrorKind::TimedOut => ServiceError::Fetch(FetchError::Timeout),
+        _ => ServiceError::Fetch(FetchError::Network),
+    }
+}
+
+// as msg to crawle a page and parse as new item
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct SpiderItem {
+    pub url: String,
+}
+
+impl Message for SpiderItem {
+    type Result = Result<NewItem, ServiceError>;
+}
+
+// handle msg from api::item.get_list per url miss in db, and from bot::crawler
+impl Handler<SpiderItem> for Spider {
+    type Result = Result<NewItem, ServiceError>;
+
+    fn handle(&mut self, sp: SpiderItem, _: &mut Self::Context) -> Self::Result {
+        check_url(&sp.url)?;
+        if !robots_allowed(&self.0, &sp.url, BOT_NAME)? {
+            return Err(ServiceError::Forbidden);
+        }
+        let page = WebPage::new(&self.0, &sp.url)?;
+
+        Ok(page.into_item())
+    }
+}
+
+// as msg to check if url and cover alive, parse a draft if url alive
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct CheckLink {
+    pub url: String,
+    pub cover: String,
+}
+
+impl Message for CheckLink {
+    type Result = Result<LinkReport, ServiceError>;
+}
+
+#[derive(Debug, Clone)]
+pub struct LinkReport {
+    pub url_status: i16, // 0 if not checked, -1 if unreachable
+    pub cover_status: i16,
+    pub draft: Option<NewItem>,
+}
+
+// handle msg from bot::linkcheck
+impl Handler<CheckLink> for Spider {
+    type Result = Result<LinkReport, ServiceError>;
+
+    fn handle(&mut self, cl: CheckLink, _: &mut Self::Context) -> Self::Result {
+        let url_status = link_status(&self.0, &cl.url);
+        let cover_status = link_status(&self.0, &cl.cover);
+        // a draft to compare, a failed parse is not dead link
+        let draft = if url_status >= 200 && url_status < 300 {
+            WebPage::new(&self.0, &cl.url).ok().map(|p| p.into_item())
+        } else {
+            None
+        };
+
+        Ok(LinkReport {
+            url_status,
+            cover_status,
+            draft,
+        })
+    }
+}
+
+// http status per HEAD, GET if HEAD not allowed, 0 if skip
+fn link_status(client: &Client, url: &str) -> i16 {
+    if check_url(url).is_err() {
+        return 0;
+    }
+    match robots_allowed(client, url, BOT_NAME) {
+        Ok(true) => {}
+        _ => return 0,
+    }
+
+    let status = match client.head(url).send() {
+        Ok(res) => res.status().as_u16(),
+        Err(_) => return -1,
+    };
+    if status != 405 && status != 501 {
+        return status as i16;
+    }
+    match client.get(url).send() {
+        Ok(res) => res.status().as_u16() as i16,
+        Err(_) => -1,
+    }
+}
+
+// host w/o www., "" if invalid url
+pub fn url_domain(url: &str) -> String {
+    let host = Url::parse(url)
+        .ok()
+        .and_then(|u| u.host_str().map(|h| h.to_lowercase()))
+        .unwrap_or_default();
+
+    host.trim_start_matches("www.").to_owned()
+}
 
 #[derive(Debug, Clone)]
 pub struct WebPage {
@@ -15,22 +247,45 @@ pub struct WebPage {
 }
 
 impl WebPage {
-    pub fn new(url: &str) -> Self {
-        let mut res = reqwest::get(url).unwrap().text().unwrap();
+    // fetch page, public host only
+    pub fn new(client: &Client, url: &str) -> Result<Self, ServiceError> {
+        check_url(url)?;
+        Self::fetch(client, url)
+    }
+
+    // fetch page, html only, limit size
+    fn fetch(client: &Client, url: &str) -> Result<Self, ServiceError> {
+        let res = client.get(url).send()?.error_for_status()?;
+        let is_html = res
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|ct| ct.to_str().ok())
+            .map(|ct| ct.contains("text/html"))
+            .unwrap_or(true);
+        if !is_html {
+            return Err(ServiceError::BadRequest("Not HTML Page".into()));
+        }
+        if res.content_length().unwrap_or(0) > MAX_PAGE_SIZE {
+            return Err(ServiceError::BadRequest("Page Too Large".into()));
+        }
 
-        lazy_static! {
-            static ref Scheme_re: Regex = Regex::new(r"https?://").unwrap();
-            static ref Path_re: Regex = Regex::new(r"/.*").unwrap();
+        // content-length may be absent or lie, read at most the limit + 1
+        let mut buf: Vec<u8> = Vec::new();
+        res.take(MAX_PAGE_SIZE + 1).read_to_end(&mut buf).map_err(read_error)?;
+        if buf.len() as u64 > MAX_PAGE_SIZE {
+            return Err(ServiceError::BadRequest("Page Too Large".into()));
         }
+        let html = String::from_utf8_lossy(&buf).into_owned();
 
-        let uri = Scheme_re.replace_all(url, "");
-        let host = Path_re.replace_all(&uri, "");
-        let domain = host.replace("www.", "");
+        Ok(Self::from_html(url, html))
+    }
 
+    // parse a fetched html
+    pub fn from_html(url: &str, html: String) -> Self {
         Self {
             url: url.to_string(),
-            html: res,
-            domain,
+            html,
+            domain: url_domain(url),
         }
     }
 
@@ -53,131 +308,154 @@ impl WebPage {
         let url = self.get_url();
         let html = self.get_html();
         let domain = self.get_domain();
-        match domain.trim() {
-            "amazon.com" => parse_amz_page(url, html),
-            _ => parse_other_page(url, html),
+        let item = PARSERS.parse(&url, &domain, &html);
+        // the site as author if none parsed
+        let authors = if item.authors.trim() == "" {
+            domain
+        } else {
+            item.authors.trim().to_owned()
+        };
+
+        NewItem {
+            title: item.title.trim().to_owned(),
+            authors,
+            ..item
         }
     }
 }
 
-pub fn parse_amz_page(url: String, html: Html) -> NewItem {
-    let title_selector = Selector::parse("head > title").unwrap();
-    let protitle_selector = Selector::parse("#productTitle").unwrap();
-    let img_selector = Selector::parse("#imgBlkFront").unwrap();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::net::{TcpListener, TcpStream};
+    use std::thread;
 
-    // get html title
-    let titles: Vec<_> = html.select(&title_selector).collect();
+    // a local http server, a response per path, then close
+    fn serve() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                if let Ok(mut stream) = stream {
+                    thread::spawn(move || respond(&mut stream));
+                }
+            }
+        });
+        format!("http://{}", addr)
+    }
 
-    let mut title_text: String;
-    if titles.len() > 0 {
-        let title = titles[0];
-        title_text = title.inner_html();
-    } else {
-        title_text = "untitled".to_owned();
-    }
-
-    let title_parts: Vec<&str> = title_text.split(":").collect();
-    // try get uid, author, not always works
-    let p_len = title_parts.len() as i32;
-    //println!("{:#?} and len {}", title_parts, p_len);
-    let idx_uid = std::cmp::max(p_len - 3, 0) as usize;
-    let uid = title_parts[idx_uid];
-    let uiid = replace_sep(uid, "");
-    let idx_author = std::cmp::max(p_len - 4, 0) as usize;
-    let author = title_parts[idx_author];
-
-    // get product title
-    let ptitles: Vec<_> = html.select(&protitle_selector).collect();
-
-    let mut ptitle_text: String;
-    if ptitles.len() > 0 {
-        let title = ptitles[0];
-        ptitle_text = title.inner_html();
-    } else {
-        ptitle_text = title_text.clone();
-    }
-
-    // get cover image url
-    let imgs: Vec<_> = html.select(&img_selector).collect();
-    let mut img_src: String;
-    if imgs.len() > 0 {
-        let img = imgs[0];
-        match img.value().attr("data-a-dynamic-image") {
-            Some(src) => {
-                let src_urls: Vec<&str> = src.split(":").collect();
-                let src_url = src_urls[1];
-                let img_src_url = ("https:".to_owned() + src_url).replace("\"", "");
-                //println!("{}", img_src_url);
-                img_src = if re_test_img_url(&img_src_url) {
-                    img_src_url
-                } else {
-                    "".to_owned()
-                };
+    fn respond(stream: &mut TcpStream) {
+        let mut buf = [0u8; 4096];
+        let n = stream.read(&mut buf).unwrap_or(0);
+        let req = String::from_utf8_lossy(&buf[..n]);
+        let path = req.split_whitespace().nth(1).unwrap_or("/").to_owned();
+        let html = "text/html; charset=utf-8";
+        let (status, headers, body) = match path.as_str() {
+            "/page" => ("200 OK", format!("Content-Type: {}", html), b"<html><head><title>A Page</title></head></html>".to_vec()),
+            "/json" => ("200 OK", "Content-Type: application/json".to_owned(), b"{}".to_vec()),
+            // no content-length, read till close
+            "/big" => ("200 OK", format!("Content-Type: {}", html), vec![b'a'; MAX_PAGE_SIZE as usize + 1]),
+            "/slow" => {
+                thread::sleep(Duration::from_secs(3));
+                ("200 OK", format!("Content-Type: {}", html), b"<html></html>".to_vec())
             }
-            None => {
-                img_src = "".to_owned();
+            // /r/n redirects n times then to /page
+            p if p.starts_with("/r/") => {
+                let n = p[3..].parse::<u32>().unwrap_or(0);
+                let to = if n > 1 { format!("/r/{}", n - 1) } else { "/page".to_owned() };
+                ("302 Found", format!("Location: {}", to), Vec::new())
             }
-        }
-    } else {
-        img_src = "".to_owned();
+            _ => ("404 Not Found", "".to_owned(), Vec::new()),
+        };
+        let _ = write!(stream, "HTTP/1.1 {}\r\n{}\r\nConnection: close\r\n\r\n", status, headers);
+        let _ = stream.write_all(&body);
     }
 
-    NewItem {
-        title: ptitle_text,
-        uiid: uiid,
-        authors: author.to_owned(),
-        cover: img_src,
-        url: trim_url_qry(&url, ""),
-        category: "Book".to_owned(),
-        detail: title_text,
-        ..NewItem::new()
+    // the fixture server is on loopback, so fetch w/o the url check
+    fn fetch(client: &Client, url: &str) -> Result<WebPage, String> {
+        WebPage::fetch(client, url).map_err(|e| match e {
+            ServiceError::BadRequest(m) => m,
+            ServiceError::Fetch(f) => f.to_string(),
+            e => e.to_string(),
+        })
     }
-}
 
-pub fn parse_other_page(url: String, html: Html) -> NewItem {
-    let title_selector = Selector::parse("head > title").unwrap();
-    let img_selector = Selector::parse("img").unwrap();
+    fn open_client() -> Client {
+        build_client(Duration::from_secs(1), false).unwrap()
+    }
 
-    // get title
-    let titles: Vec<_> = html.select(&title_selector).collect();
+    #[test]
+    fn fetch_html_page() {
+        let host = serve();
+        let page = fetch(&open_client(), &format!("{}/page", host)).unwrap();
+        assert_eq!(page.get_url(), format!("{}/page", host));
+    }
 
-    let mut title_text: String;
-    if titles.len() > 0 {
-        let title = titles[0];
-        title_text = title.inner_html();
-    } else {
-        title_text = "untitled, please help to update".to_owned();
-    }
-
-    // get cover image url
-    let imgs: Vec<_> = html.select(&img_selector).collect();
-    let mut img_src: String;
-    if imgs.len() > 0 {
-        let img = imgs[0];
-        match img.value().attr("src") {
-            Some(src) => {
-                img_src = if re_test_img_url(src) {
-                    src.to_owned()
-                } else {
-                    "".to_owned()
-                };
-            }
-            None => {
-                img_src = "".to_owned();
-            }
+    #[test]
+    fn fetch_timeout() {
+        let host = serve();
+        let res = fetch(&open_client(), &format!("{}/slow", host));
+        assert_eq!(res.unwrap_err(), "Fetch Timeout");
+    }
+
+    #[test]
+    fn fetch_size_cap() {
+        let host = serve();
+        let res = fetch(&open_client(), &format!("{}/big", host));
+        assert_eq!(res.unwrap_err(), "Page Too Large");
+    }
+
+    #[test]
+    fn fetch_non_html() {
+        let host = serve();
+        let res = fetch(&open_client(), &format!("{}/json", host));
+        assert_eq!(res.unwrap_err(), "Not HTML Page");
+    }
+
+    #[test]
+    fn fetch_redirect_cap() {
+        let host = serve();
+        let client = open_client();
+        assert!(fetch(&client, &format!("{}/r/{}", host, MAX_REDIRECTS)).is_ok());
+        let res = fetch(&client, &format!("{}/r/{}", host, MAX_REDIRECTS + 1));
+        assert_eq!(res.unwrap_err(), "Too Many Redirects");
+    }
+
+    #[test]
+    fn redirect_to_private_blocked() {
+        let host = serve();
+        let guarded = build_client(Duration::from_secs(1), true).unwrap();
+        assert!(fetch(&open_client(), &format!("{}/r/1", host)).is_ok());
+        assert!(fetch(&guarded, &format!("{}/r/1", host)).is_err());
+    }
+
+    #[test]
+    fn check_url_private_blocked() {
+        for u in &[
+            "http://127.0.0.1/",
+            "http://localhost:8083/api",
+            "http://10.1.2.3/",
+            "http://192.168.0.1/",
+            "http://172.16.0.1/",
+            "http://169.254.169.254/latest/meta-data",
+            "http://0.0.0.0/",
+            "http://[::1]/",
+        ] {
+            assert!(check_url(u).is_err(), "{}", u);
         }
-    } else {
-        img_src = "".to_owned();
     }
 
-    NewItem {
-        title: title_text.clone(),
-        cover: img_src,
-        url: url,
-        category: "WebPage".to_owned(),
-        detail: title_text,
-        ..NewItem::new()
+    #[test]
+    fn public_ip_rules() {
+        let ip = |s: &str| s.parse::<IpAddr>().unwrap();
+        assert!(public_ip(&ip("8.8.8.8")));
+        assert!(public_ip(&ip("2001:4860:4860::8888")));
+        assert!(!public_ip(&ip("100.64.0.1")));
+        assert!(!public_ip(&ip("::ffff:127.0.0.1")));
+        assert!(!public_ip(&ip("::ffff:10.0.0.1")));
+        assert!(!public_ip(&ip("fe80::1")));
+        assert!(!public_ip(&ip("fd00::1")));
+        assert!(!public_ip(&ip("::")));
     }
 }
-
-// todo: more specific parse
@@ -0,0 +1,661 @@
+// site parsers to extract item metadata from page, registry per domain pattern
+
+use scraper::{ElementRef, Html, Selector};
+use serde_json::Value;
+
+use crate::model::item::NewItem;
+use crate::model::{normalize_uiid, re_test_img_url, trim_url_qry, uiid_kind};
+
+pub trait SiteParser: Send + Sync {
+    // for log
+    fn name(&self) -> &'static str;
+    // parse page, the fields not found keep ""
+    fn parse(&self, url: &str, html: &Html) -> NewItem;
+}
+
+// parsers in order, the former takes priority per field
+pub struct Registry {
+    parsers: Vec<(String, Box<dyn SiteParser>)>, // (domain pattern, parser)
+}
+
+impl Registry {
+    pub fn new() -> Self {
+        Registry {
+            parsers: Vec::new(),
+        }
+    }
+
+    // pattern: "*" for all, "amazon.*" for any tld, "coursera.org" for the domain and sub
+    pub fn register(&mut self, pattern: &str, parser: Box<dyn SiteParser>) {
+        self.parsers.push((pattern.to_owned(), parser));
+    }
+
+    // parse per all matched parsers, fill the fields per order
+    pub fn parse(&self, url: &str, domain: &str, html: &Html) -> NewItem {
+        self.parsers
+            .iter()
+            .filter(|(p, _)| domain_match(p, domain))
+            .fold(NewItem::new(), |item, (_, parser)| {
+                debug!("parse {} per {}", url, parser.name());
+                fill_item(item, parser.parse(url, html))
+            })
+    }
+}
+
+lazy_static! {
+    pub static ref PARSERS: Registry = {
+        let mut reg = Registry::new();
+        // specific site first
+        reg.register("amazon.*", Box::new(AmazonParser));
+        reg.register("goodreads.com", Box::new(SiteHint("Book", "")));
+        reg.register("oreilly.com", Box::new(SiteHint("Book", "O'Reilly")));
+        reg.register("manning.com", Box::new(SiteHint("Book", "Manning")));
+        reg.register("coursera.org", Box::new(SiteHint("Course", "Coursera")));
+        reg.register("udemy.com", Box::new(SiteHint("Course", "Udemy")));
+        reg.register("edx.org", Box::new(SiteHint("Course", "edX")));
+        reg.register("khanacademy.org", Box::new(SiteHint("Course", "Khan Academy")));
+        // then structured data, generic
+        reg.register("*", Box::new(JsonLdParser));
+        reg.register("*", Box::new(OpenGraphParser));
+        reg.register("*", Box::new(TwitterCardParser));
+        // last resort
+        reg.register("*", Box::new(HtmlParser));
+        reg
+    };
+}
+
+pub fn domain_match(pattern: &str, domain: &str) -> bool {
+    if pattern == "*" {
+        true
+    } else if pattern.ends_with(".*") {
+        let base = pattern.trim_end_matches(".*");
+        domain.starts_with(&format!("{}.", base)) || domain.contains(&format!(".{}.", base))
+    } else {
+        domain == pattern || domain.ends_with(&format!(".{}", pattern))
+    }
+}
+
+// fill the "" field of item per more
+pub fn fill_item(item: NewItem, more: NewItem) -> NewItem {
+    let pick = |a: String, b: String| if a.trim() == "" { b } else { a };
+    NewItem {
+        title: pick(item.title, more.title),
+        uiid: pick(item.uiid, more.uiid),
+        authors: pick(item.authors, more.authors),
+        pub_at: pick(item.pub_at, more.pub_at),
+        publisher: pick(item.publisher, more.publisher),
+        category: pick(item.category, more.category),
+        url: pick(item.url, more.url),
+        cover: pick(item.cover, more.cover),
+        edition: pick(item.edition, more.edition),
+        detail: pick(item.detail, more.detail),
+    }
+}
+
+// some helper to extract
+
+fn select_first<'a>(html: &'a Html, sel: &str) -> Option<ElementRef<'a>> {
+    let selector = Selector::parse(sel).ok()?;
+    html.select(&selector).next()
+}
+
+fn elem_text(el: ElementRef) -> String {
+    el.text().collect::<Vec<_>>().join(" ").split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn select_text(html: &Html, sel: &str) -> String {
+    select_first(html, sel).map(elem_text).unwrap_or_default()
+}
+
+fn select_texts(html: &Html, sel: &str) -> Vec<String> {
+    match Selector::parse(sel) {
+        Ok(selector) => html
+            .select(&selector)
+            .map(elem_text)
+            .filter(|t| t != "")
+            .collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+fn select_attr(html: &Html, sel: &str, attr: &str) -> String {
+    select_first(html, sel)
+        .and_then(|el| el.value().attr(attr))
+        .map(|a| a.trim().to_owned())
+        .unwrap_or_default()
+}
+
+fn first_of(a: String, b: String) -> String {
+    if a != "" {
+        a
+    } else {
+        b
+    }
+}
+
+// <meta property|name="key" content="">
+fn meta_content(html: &Html, key: &str) -> String {
+    first_of(
+        select_attr(html, &format!("meta[property=\"{}\"]", key), "content"),
+        select_attr(html, &format!("meta[name=\"{}\"]", key), "content"),
+    )
+}
+
+fn img_or_empty(src: String) -> String {
+    if re_test_img_url(&src) {
+        src
+    } else {
+        "".to_owned()
+    }
+}
+
+// isbn etc. in canonical form, "" if checksum fails
+fn clean_uiid(text: &str) -> String {
+    normalize_uiid(text).unwrap_or_default()
+}
+
+// structured data: <script type="application/ld+json">, schema.org Book|Course|CreativeWork
+pub struct JsonLdParser;
+
+impl JsonLdParser {
+    // flatten per array and @graph
+    fn nodes(v: Value) -> Vec<Value> {
+        match v {
+            Value::Array(arr) => arr.into_iter().flat_map(Self::nodes).collect(),
+            Value::Object(ref obj) if obj.contains_key("@graph") => {
+                Self::nodes(obj["@graph"].clone())
+            }
+            Value::Object(_) => vec![v],
+            _ => Vec::new(),
+        }
+    }
+
+    fn type_of(v: &Value) -> String {
+        let types: Vec<String> = match &v["@type"] {
+            Value::String(s) => vec![s.to_owned()],
+            Value::Array(a) => a.iter().filter_map(|t| t.as_str()).map(|t| t.to_owned()).collect(),
+            _ => Vec::new(),
+        };
+        for ty in &["Book", "Course", "CreativeWork"] {
+            if types.iter().any(|t| t == ty) {
+                return ty.to_string();
+            }
+        }
+        "".to_owned()
+    }
+
+    // "x" or {"name": "x"} or [..]
+    fn names(v: &Value) -> Vec<String> {
+        match v {
+            Value::String(s) => vec![s.trim().to_owned()],
+            Value::Object(o) => o
+                .get("name")
+                .and_then(|n| n.as_str())
+                .map(|n| vec![n.trim().to_owned()])
+                .unwrap_or_default(),
+            Value::Array(a) => a.iter().flat_map(Self::names).collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    // "url" or {"url": "url"} or [..]
+    fn image(v: &Value) -> String {
+        match v {
+            Value::String(s) => s.trim().to_owned(),
+            Value::Object(o) => o
+                .get("url")
+                .and_then(|u| u.as_str())
+                .unwrap_or("")
+                .trim()
+                .to_owned(),
+            Value::Array(a) => a.first().map(Self::image).unwrap_or_default(),
+            _ => "".to_owned(),
+        }
+    }
+
+    fn text(v: &Value) -> String {
+        match v {
+            Value::String(s) => s.trim().to_owned(),
+            Value::Number(n) => n.to_string(),
+            _ => "".to_owned(),
+        }
+    }
+}
+
+impl SiteParser for JsonLdParser {
+    fn name(&self) -> &'static str {
+        "json-ld"
+    }
+
+    fn parse(&self, url: &str, html: &Html) -> NewItem {
+        let selector = match Selector::parse("script[type=\"application/ld+json\"]") {
+            Ok(s) => s,
+            Err(_) => return NewItem::new(),
+        };
+        let node = html
+            .select(&selector)
+            .filter_map(|el| serde_json::from_str::<Value>(&el.text().collect::<String>()).ok())
+            .flat_map(Self::nodes)
+            .find(|n| Self::type_of(n) != "");
+        let node = match node {
+            Some(n) => n,
+            None => return NewItem::new(),
+        };
+
+        let ty = Self::type_of(&node);
+        let uiid = match ty.as_str() {
+            "Course" => Self::text(&node["courseCode"]),
+            _ => Self::text(&node["isbn"]),
+        };
+        let publisher = match ty.as_str() {
+            "Course" => Self::names(&node["provider"]),
+            _ => Self::names(&node["publisher"]),
+        };
+        let edition = {
+            let ed = Self::text(&node["bookEdition"]);
+            if ed != "" {
+                ed
+            } else {
+                Self::text(&node["bookFormat"]).replace("http://schema.org/", "")
+            }
+        };
+
+        NewItem {
+            title: Self::text(&node["name"]),
+            uiid: clean_uiid(&uiid),
+            authors: Self::names(&node["author"]).join(", "),
+            pub_at: Self::text(&node["datePublished"]),
+            publisher: publisher.join(", "),
+            category: ty,
+            url: url.to_owned(),
+            cover: img_or_empty(Self::image(&node["image"])),
+            edition,
+            detail: Self::text(&node["description"]),
+        }
+    }
+}
+
+// <meta property="og:*">, and book:* of og type book
+pub struct OpenGraphParser;
+
+impl SiteParser for OpenGraphParser {
+    fn name(&self) -> &'static str {
+        "opengraph"
+    }
+
+    fn parse(&self, _url: &str, html: &Html) -> NewItem {
+        let og_type = meta_content(html, "og:type").to_lowercase();
+        let is_book = og_type == "book" || og_type == "books.book";
+        let category = if is_book { "Book" } else { "" };
+        let (isbn, author, release) = if is_book {
+            (
+                first_of(meta_content(html, "book:isbn"), meta_content(html, "books:isbn")),
+                meta_content(html, "book:author"),
+                meta_content(html, "book:release_date"),
+            )
+        } else {
+            ("".to_owned(), "".to_owned(), "".to_owned())
+        };
+
+        NewItem {
+            title: meta_content(html, "og:title"),
+            uiid: clean_uiid(&isbn),
+            authors: author,
+            pub_at: release,
+            publisher: meta_content(html, "og:site_name"),
+            category: category.to_owned(),
+            cover: img_or_empty(meta_content(html, "og:image")),
+            detail: meta_content(html, "og:description"),
+            ..NewItem::new()
+        }
+    }
+}
+
+// <meta name="twitter:*">
+pub struct TwitterCardParser;
+
+impl SiteParser for TwitterCardParser {
+    fn name(&self) -> &'static str {
+        "twitter-card"
+    }
+
+    fn parse(&self, _url: &str, html: &Html) -> NewItem {
+        NewItem {
+            title: meta_content(html, "twitter:title"),
+            cover: img_or_empty(meta_content(html, "twitter:image")),
+            detail: meta_content(html, "twitter:description"),
+            ..NewItem::new()
+        }
+    }
+}
+
+// category and publisher of known book or course site
+pub struct SiteHint(pub &'static str, pub &'static str); // (category, publisher)
+
+impl SiteParser for SiteHint {
+    fn name(&self) -> &'static str {
+        "site-hint"
+    }
+
+    fn parse(&self, _url: &str, _html: &Html) -> NewItem {
+        NewItem {
+            category: self.0.to_owned(),
+            publisher: self.1.to_owned(),
+            ..NewItem::new()
+        }
+    }
+}
+
+// amazon product page, per the element ids, title split as last resort
+pub struct AmazonParser;
+
+impl AmazonParser {
+    // "Title: Author: 9781234567890: Amazon.com: Books"
+    fn split_title(title: &str) -> (String, String) {
+        let parts: Vec<&str> = title.split(':').map(|p| p.trim()).collect();
+        let len = parts.len();
+        if len < 4 {
+            return ("".to_owned(), "".to_owned());
+        }
+        let uid = clean_uiid(parts[len - 3]);
+        let uid = if uiid_kind(&uid) != "" {
+            uid
+        } else {
+            "".to_owned()
+        };
+        let author = if len >= 5 { parts[len - 4].to_owned() } else { "".to_owned() };
+        (uid, author)
+    }
+
+    // ISBN-13 first, then ISBN-10 or ASIN, in detail bullets
+    fn detail_uiid(html: &Html) -> String {
+        let details = select_texts(
+            html,
+            "#detailBullets_feature_div li, #productDetailsTable li, #detail_bullets_id li",
+        );
+        for key in &["ISBN-13", "ISBN-10", "ASIN"] {
+            if let Some(d) = details.iter().find(|d| d.starts_with(key)) {
+                let v = d.splitn(2, ':').nth(1).unwrap_or("");
+                let uid = clean_uiid(v);
+                if uid != "" {
+                    return uid;
+                }
+            }
+        }
+        "".to_owned()
+    }
+
+    // data-a-dynamic-image: {"url": [w, h], ...}
+    fn dynamic_image(html: &Html) -> String {
+        let dyn_img = first_of(
+            select_attr(html, "#imgBlkFront", "data-a-dynamic-image"),
+            select_attr(html, "#landingImage", "data-a-dynamic-image"),
+        );
+        serde_json::from_str::<Value>(&dyn_img)
+            .ok()
+            .and_then(|v| v.as_object().and_then(|o| o.keys().next().cloned()))
+            .unwrap_or_default()
+    }
+}
+
+impl SiteParser for AmazonParser {
+    fn name(&self) -> &'static str {
+        "amazon"
+    }
+
+    fn parse(&self, url: &str, html: &Html) -> NewItem {
+        let page_title = select_text(html, "head > title");
+        let (title_uid, title_author) = Self::split_title(&page_title);
+
+        let uid = Self::detail_uiid(html);
+        let uiid = if uid != "" { uid } else { title_uid };
+        let byline = select_texts(html, "#bylineInfo .author > a, #bylineInfo .contributorNameID");
+        let authors = if byline.len() > 0 { byline.join(", ") } else { title_author };
+
+        NewItem {
+            title: select_text(html, "#productTitle"),
+            uiid,
+            authors,
+            url: trim_url_qry(url, ""),
+            cover: img_or_empty(Self::dynamic_image(html)),
+            category: "Book".to_owned(),
+            edition: select_text(html, "#productSubtitle"),
+            detail: page_title,
+            ..NewItem::new()
+        }
+    }
+}
+
+// plain html: <title> and the first image
+pub struct HtmlParser;
+
+impl SiteParser for HtmlParser {
+    fn name(&self) -> &'static str {
+        "html"
+    }
+
+    fn parse(&self, url: &str, html: &Html) -> NewItem {
+        let title = select_text(html, "head > title");
+        let title = if title == "" {
+            "untitled, please help to update".to_owned()
+        } else {
+            title
+        };
+        let cover = match Selector::parse("img[src]") {
+            Ok(s) => html
+                .select(&s)
+                .filter_map(|img| img.value().attr("src"))
+                .map(|src| src.trim().to_owned())
+                .find(|src| re_test_img_url(src))
+                .unwrap_or_default(),
+            Err(_) => "".to_owned(),
+        };
+
+        NewItem {
+            title: title.clone(),
+            url: url.to_owned(),
+            cover,
+            category: "WebPage".to_owned(),
+            detail: title,
+            ..NewItem::new()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const URL: &str = "https://example.com/a-page";
+
+    fn doc(body: &str) -> Html {
+        Html::parse_document(body)
+    }
+
+    #[test]
+    fn domain_patterns() {
+        assert!(domain_match("*", "example.com"));
+        assert!(domain_match("amazon.*", "amazon.com"));
+        assert!(domain_match("amazon.*", "amazon.co.uk"));
+        assert!(domain_match("amazon.*", "smile.amazon.com"));
+        assert!(!domain_match("amazon.*", "notamazon.com"));
+        assert!(domain_match("coursera.org", "coursera.org"));
+        assert!(domain_match("coursera.org", "learn.coursera.org"));
+        assert!(!domain_match("coursera.org", "evilcoursera.org"));
+        assert!(!domain_match("coursera.org", "coursera.org.evil.com"));
+    }
+
+    #[test]
+    fn amazon_per_elements() {
+        let html = doc(r#"<html><head><title>Page Title</title></head><body>
+            <span id="productTitle"> Introduction to Algorithms </span>
+            <span id="productSubtitle">3rd Edition</span>
+            <div id="bylineInfo"><span class="author"><a>Thomas H. Cormen</a></span>
+                <span class="author"><a>Charles E. Leiserson</a></span></div>
+            <img id="landingImage" data-a-dynamic-image='{"https://images.example.com/c.jpg": [500, 600]}'>
+            <div id="detailBullets_feature_div"><ul>
+                <li>Publisher : MIT Press</li>
+                <li>ISBN-10 : 0262033844</li>
+            </ul></div>
+            </body></html>"#);
+        let item = AmazonParser.parse("https://www.amazon.com/dp/0262033844/ref=sr_1_1?kw=x", &html);
+        assert_eq!(item.title, "Introduction to Algorithms");
+        assert_eq!(item.uiid, "9780262033848");
+        assert_eq!(item.authors, "Thomas H. Cormen, Charles E. Leiserson");
+        assert_eq!(item.url, "https://www.amazon.com/dp/0262033844");
+        assert_eq!(item.cover, "https://images.example.com/c.jpg");
+        assert_eq!(item.edition, "3rd Edition");
+        assert_eq!(item.category, "Book");
+    }
+
+    #[test]
+    fn amazon_per_page_title() {
+        let html = doc("<html><head><title>Introduction to Algorithms: Cormen, Thomas H.: 9780262033848: Amazon.com: Books</title></head></html>");
+        let item = AmazonParser.parse("https://www.amazon.com/dp/0262033844", &html);
+        assert_eq!(item.uiid, "9780262033848");
+        assert_eq!(item.authors, "Cormen, Thomas H.");
+        // not an isbn in place
+        let html = doc("<html><head><title>A: B: C: Amazon.com: Books</title></head></html>");
+        let item = AmazonParser.parse("https://www.amazon.com/dp/x", &html);
+        assert_eq!(item.uiid, "");
+    }
+
+    #[test]
+    fn json_ld_graph() {
+        let html = doc(r#"<html><head><script type="application/ld+json">
+            {"@context": "https://schema.org", "@graph": [
+                {"@type": "WebSite", "name": "A Site"},
+                {"@type": "Book", "name": "A Book", "isbn": "978-0-262-03384-8",
+                 "author": [{"@type": "Person", "name": "Ann"}, {"name": "Bob"}],
+                 "publisher": {"name": "MIT Press"}, "datePublished": "2009",
+                 "image": {"url": "https://example.com/cover.jpg"},
+                 "bookEdition": "3rd", "description": "a desc"}
+            ]}
+            </script></head></html>"#);
+        let item = JsonLdParser.parse(URL, &html);
+        assert_eq!(item.title, "A Book");
+        assert_eq!(item.uiid, "9780262033848");
+        assert_eq!(item.authors, "Ann, Bob");
+        assert_eq!(item.publisher, "MIT Press");
+        assert_eq!(item.pub_at, "2009");
+        assert_eq!(item.category, "Book");
+        assert_eq!(item.url, URL);
+        assert_eq!(item.cover, "https://example.com/cover.jpg");
+        assert_eq!(item.edition, "3rd");
+        assert_eq!(item.detail, "a desc");
+    }
+
+    #[test]
+    fn json_ld_array() {
+        let html = doc(r#"<html><head>
+            <script type="application/ld+json">not json</script>
+            <script type="application/ld+json">
+            [{"@type": "Organization", "name": "Org"},
+             {"@type": ["Thing", "Course"], "name": "Machine Learning", "courseCode": "CS229",
+              "provider": "Stanford", "image": ["https://example.com/ml.png", "https://example.com/x.png"]}]
+            </script></head></html>"#);
+        let item = JsonLdParser.parse(URL, &html);
+        assert_eq!(item.title, "Machine Learning");
+        assert_eq!(item.category, "Course");
+        assert_eq!(item.uiid, "CS229");
+        assert_eq!(item.publisher, "Stanford");
+        assert_eq!(item.cover, "https://example.com/ml.png");
+        // none of known types
+        let html = doc(r#"<script type="application/ld+json">{"@type": "Thing", "name": "x"}</script>"#);
+        assert_eq!(JsonLdParser.parse(URL, &html).title, "");
+    }
+
+    #[test]
+    fn open_graph_book() {
+        let html = doc(r#"<html><head>
+            <meta property="og:type" content="book">
+            <meta property="og:title" content="A Book">
+            <meta property="og:site_name" content="A Press">
+            <meta property="og:image" content="https://example.com/og.jpg">
+            <meta property="og:description" content="og desc">
+            <meta property="book:isbn" content="0262033844">
+            <meta property="book:author" content="Ann">
+            <meta property="book:release_date" content="2009-07-31">
+            </head></html>"#);
+        let item = OpenGraphParser.parse(URL, &html);
+        assert_eq!(item.title, "A Book");
+        assert_eq!(item.uiid, "9780262033848");
+        assert_eq!(item.authors, "Ann");
+        assert_eq!(item.pub_at, "2009-07-31");
+        assert_eq!(item.publisher, "A Press");
+        assert_eq!(item.category, "Book");
+        assert_eq!(item.cover, "https://example.com/og.jpg");
+        assert_eq!(item.detail, "og desc");
+    }
+
+    #[test]
+    fn open_graph_not_book() {
+        let html = doc(r#"<html><head>
+            <meta property="og:type" content="article">
+            <meta name="og:title" content="An Article">
+            <meta property="og:image" content="https://example.com/og.webp">
+            <meta property="book:isbn" content="0262033844">
+            </head></html>"#);
+        let item = OpenGraphParser.parse(URL, &html);
+        assert_eq!(item.title, "An Article");
+        assert_eq!(item.uiid, "");
+        assert_eq!(item.category, "");
+        assert_eq!(item.cover, "");
+    }
+
+    #[test]
+    fn twitter_card() {
+        let html = doc(r#"<html><head>
+            <meta name="twitter:title" content="A Card">
+            <meta name="twitter:image" content="https://example.com/tw.png">
+            <meta name="twitter:description" content="tw desc">
+            </head></html>"#);
+        let item = TwitterCardParser.parse(URL, &html);
+        assert_eq!(item.title, "A Card");
+        assert_eq!(item.cover, "https://example.com/tw.png");
+        assert_eq!(item.detail, "tw desc");
+    }
+
+    #[test]
+    fn html_fallback() {
+        let html = doc(r#"<html><head><title> A   Page </title></head><body>
+            <img src="/relative.jpg"><img src="https://example.com/a.gif"></body></html>"#);
+        let item = HtmlParser.parse(URL, &html);
+        assert_eq!(item.title, "A Page");
+        assert_eq!(item.url, URL);
+        assert_eq!(item.cover, "https://example.com/a.gif");
+        assert_eq!(item.category, "WebPage");
+        let item = HtmlParser.parse(URL, &doc("<html><body></body></html>"));
+        assert_eq!(item.title, "untitled, please help to update");
+    }
+
+    #[test]
+    fn registry_field_precedence() {
+        let mut reg = Registry::new();
+        reg.register("amazon.*", Box::new(AmazonParser));
+        reg.register("*", Box::new(JsonLdParser));
+        reg.register("*", Box::new(OpenGraphParser));
+        reg.register("*", Box::new(HtmlParser));
+        let html = doc(r#"<html><head><title>Html Title</title>
+            <script type="application/ld+json">{"@type": "Book", "name": "LD Title"}</script>
+            <meta property="og:title" content="OG Title">
+            <meta property="og:image" content="https://example.com/og.jpg">
+            <meta property="og:site_name" content="OG Site">
+            </head></html>"#);
+        let item = reg.parse(URL, "example.com", &html);
+        // the former wins, the latter fills the empty
+        assert_eq!(item.title, "LD Title");
+        assert_eq!(item.category, "Book");
+        assert_eq!(item.cover, "https://example.com/og.jpg");
+        assert_eq!(item.publisher, "OG Site");
+        assert_eq!(item.url, URL);
+        assert_eq!(item.detail, "Html Title");
+        // the amazon parser only on its domain
+        let item = reg.parse(URL, "amazon.com", &html);
+        assert_eq!(item.category, "Book");
+        assert_eq!(item.url, URL);
+        let html = doc("<html><head><title>Html Title</title></head></html>");
+        assert_eq!(reg.parse(URL, "example.com", &html).category, "WebPage");
+        assert_eq!(reg.parse(URL, "amazon.com", &html).category, "Book");
+    }
+}
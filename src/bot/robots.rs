@@ -0,0 +1,201 @@
+// robots.txt check, cached per host
+
+use reqwest::{Client, StatusCode, Url};
+use std::collections::HashMap;
+use std::io::Read;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use super::read_error;
+use crate::errors::{FetchError, ServiceError};
+
+pub const ROBOTS_TTL: u64 = 3600; // seconds to cache robots.txt
+pub const ROBOTS_SIZE: u64 = 512 * 1024; // bytes, ignore the rest
+pub const ROBOTS_CACHE_MAX: usize = 1024; // hosts in cache
+
+// path rules of the group for our bot, or for *
+#[derive(Debug, Clone, Default)]
+pub struct Robots {
+    allow: Vec<String>,
+    disallow: Vec<String>,
+}
+
+impl Robots {
+    // bot: the token in user agent, e.g. rutbot
+    pub fn parse(txt: &str, bot: &str) -> Self {
+        let bot = bot.to_lowercase();
+        let mut own: Option<Robots> = None;
+        let mut any: Option<Robots> = None;
+        let mut agents: Vec<String> = Vec::new();
+        let mut in_rules = false;
+
+        for line in txt.lines() {
+            let line = line.splitn(2, '#').next().unwrap_or("").trim();
+            let mut kv = line.splitn(2, ':');
+            let key = kv.next().unwrap_or("").trim().to_lowercase();
+            let val = kv.next().unwrap_or("").trim().to_owned();
+            match key.as_str() {
+                "user-agent" => {
+                    // a new group after rules
+                    if in_rules {
+                        agents.clear();
+                        in_rules = false;
+                    }
+                    agents.push(val.to_lowercase());
+                }
+                "allow" | "disallow" => {
+                    in_rules = true;
+                    for a in &agents {
+                        let group = if a == "*" {
+                            any.get_or_insert_with(Robots::default)
+                        } else if bot.starts_with(a.as_str()) {
+                            own.get_or_insert_with(Robots::default)
+                        } else {
+                            continue;
+                        };
+                        // empty disallow: allow all
+                        let path = val.trim_end_matches('*').to_owned();
+                        if key == "allow" {
+                            group.allow.push(path);
+                        } else if path != "" {
+                            group.disallow.push(path);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        own.or(any).unwrap_or_default()
+    }
+
+    // the longest match wins, allow if tie
+    pub fn allowed(&self, path: &str) -> bool {
+        let longest = |rules: &Vec<String>| {
+            rules
+                .iter()
+                .filter(|r| path.starts_with(r.trim_end_matches('$')))
+                .map(|r| r.len())
+                .max()
+        };
+        match (longest(&self.allow), longest(&self.disallow)) {
+            (_, None) => true,
+            (None, Some(_)) => false,
+            (Some(a), Some(d)) => a >= d,
+        }
+    }
+}
+
+lazy_static! {
+    static ref ROBOTS_CACHE: Mutex<HashMap<String, (Instant, Robots)>> =
+        Mutex::new(HashMap::new());
+}
+
+// fetch robots.txt of the host, 4xx as allow all
+fn fetch_robots(client: &Client, url: &Url, bot: &str) -> Result<Robots, ServiceError> {
+    let robots_url = url
+        .join("/robots.txt")
+        .map_err(|_| ServiceError::BadRequest("Invalid URL".into()))?;
+    let res = client.get(robots_url).send()?;
+    let status = res.status();
+    if status.is_client_error() {
+        return Ok(Robots::default());
+    }
+    if status != StatusCode::OK {
+        return Err(ServiceError::Fetch(FetchError::Status(status.as_u16())));
+    }
+    let mut txt = String::new();
+    res.take(ROBOTS_SIZE).read_to_string(&mut txt).map_err(read_error)?;
+
+    Ok(Robots::parse(&txt, bot))
+}
+
+// evict the expired if full, then the oldest if still full
+fn cache_robots(
+    cache: &mut HashMap<String, (Instant, Robots)>,
+    host: String,
+    robots: Robots,
+    ttl: Duration,
+) {
+    if cache.len() >= ROBOTS_CACHE_MAX {
+        cache.retain(|_, (at, _)| at.elapsed() < ttl);
+    }
+    if cache.len() >= ROBOTS_CACHE_MAX {
+        let oldest = cache
+            .iter()
+            .min_by_key(|(_, (at, _))| *at)
+            .map(|(h, _)| h.clone());
+        if let Some(h) = oldest {
+            cache.remove(&h);
+        }
+    }
+    cache.insert(host, (Instant::now(), robots));
+}
+
+pub fn robots_allowed(client: &Client, url: &str, bot: &str) -> Result<bool, ServiceError> {
+    let u = Url::parse(url).map_err(|_| ServiceError::BadRequest("Invalid URL".into()))?;
+    let host = format!("{}://{}", u.scheme(), u.host_str().unwrap_or(""));
+    let path = match u.query() {
+        Some(q) => format!("{}?{}", u.path(), q),
+        None => u.path().to_owned(),
+    };
+
+    let cached = ROBOTS_CACHE
+        .lock()
+        .ok()
+        .and_then(|c| c.get(&host).cloned())
+        .filter(|(at, _)| at.elapsed() < Duration::from_secs(ROBOTS_TTL))
+        .map(|(_, r)| r);
+    let robots = match cached {
+        Some(r) => r,
+        None => {
+            let r = fetch_robots(client, &u, bot)?;
+            if let Ok(mut c) = ROBOTS_CACHE.lock() {
+                cache_robots(&mut c, host, r.clone(), Duration::from_secs(ROBOTS_TTL));
+            }
+            r
+        }
+    };
+
+    Ok(robots.allowed(&path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn robots_cache_bounded() {
+        let ttl = Duration::from_secs(10);
+        let now = Instant::now();
+        let mut cache = HashMap::new();
+        cache.insert("http://stale".to_owned(), (now - Duration::from_secs(11), Robots::default()));
+        // h1 the oldest, all in ttl
+        for i in 1..ROBOTS_CACHE_MAX {
+            let at = now - Duration::from_millis((ROBOTS_CACHE_MAX - i) as u64);
+            cache.insert(format!("http://h{}", i), (at, Robots::default()));
+        }
+        assert_eq!(cache.len(), ROBOTS_CACHE_MAX);
+        // full: the expired goes first
+        cache_robots(&mut cache, "http://new".to_owned(), Robots::default(), ttl);
+        assert_eq!(cache.len(), ROBOTS_CACHE_MAX);
+        assert!(!cache.contains_key("http://stale"));
+        // full w/o expired: the oldest goes
+        cache_robots(&mut cache, "http://newer".to_owned(), Robots::default(), ttl);
+        assert_eq!(cache.len(), ROBOTS_CACHE_MAX);
+        assert!(!cache.contains_key("http://h1"));
+        assert!(cache.contains_key("http://new"));
+    }
+
+    #[test]
+    fn robots_rules() {
+        let txt = "User-agent: *\nDisallow: /private\n\nUser-agent: rutbot\nDisallow: /no\nAllow: /no/yes\n";
+        let own = Robots::parse(txt, "rutbot");
+        assert!(!own.allowed("/no/x"));
+        assert!(own.allowed("/no/yes/x"));
+        assert!(own.allowed("/private"));
+        let any = Robots::parse(txt, "otherbot");
+        assert!(!any.allowed("/private/x"));
+        assert!(any.allowed("/no"));
+    }
+}
@@ -1,27 +1,46 @@
 // item typed model and msg handler
 
 use actix::Handler;
-use chrono::Utc;
+use chrono::{Duration, NaiveDateTime, Utc};
 use diesel::prelude::*;
+use diesel::sql_query;
+use diesel::sql_types::{BigInt, Text};
 use diesel::{
     self, dsl::any, ExpressionMethods, 
     PgTextExpressionMethods, QueryDsl, RunQueryDsl
 };
+use serde_json::json;
 use uuid::Uuid;
 
-use crate::bot::WebPage;
 use crate::errors::ServiceError;
 use crate::model::item::{
-    Collect, CollectItem, DelCollect, Item, NewItem, 
-    NewStarItem, QueryCollect, QueryCollects, QueryItem, 
-    QueryItems, StarItem, StarItemStatus, UpdateCollect, UpdateItem,
+    is_dead_status, Collect, CollectItem, DecideSuggest, DelCollect, DupItems, Item,
+    ItemRedirect, ItemSuggest, MergeItem, NewItem, NewStarItem, QueryCollect, QueryCollects,
+    QueryDupItems, QueryItem, QueryItems, QueryLinkCheck, QuerySuggests, ReorderCollect,
+    SaveLinkCheck, StarItem, StarItemStatus, UpdateCollect, UpdateItem, LINK_CHECK_AGE,
+};
+use crate::model::msg::{
+    CollectMsg, CollectsMsg, DupItemsMsg, ItemListMsg, ItemMsg, Msg, StarItemMsg,
+    SuggestListMsg,
 };
-use crate::model::msg::{CollectMsg, CollectsMsg, ItemListMsg, ItemMsg, Msg, StarItemMsg};
 use crate::model::rut::Rut;
-use crate::model::PER_PAGE;
-use crate::util::share::gen_slug;
+use crate::model::tag::TagItem;
+use crate::model::{normalize_uiid, uiid_kind, uiid_variants};
+use crate::model::user::EIDT_PERMIT;
+use crate::db::tag::{tag_descendants, tagged_page, untag_upkeep};
+use crate::db::user::{add_timeline, user_can};
+use crate::util::share::{
+    encode_cursor, gen_slug, parse_int_cursor, parse_time_cursor, sort_by_ids, time_cursor,
+};
 use crate::Dba;
 
+sql_function!(fn lower(x: Text) -> Text);
+
+// the variants of uiid in lowercase, to match per lower(uiid)
+fn uiid_keys(u: &str) -> Vec<String> {
+    uiid_variants(u).into_iter().map(|v| v.to_lowercase()).collect()
+}
+
 // handle msg from api::item.submit_item
 impl Handler<NewItem> for Dba {
     type Result = Result<ItemMsg, ServiceError>;
@@ -35,24 +54,26 @@ impl Handler<NewItem> for Dba {
         let s_uiid = &submit.uiid;
         let s_url = &submit.url;
         if s_uiid.trim() != "" {
-            let check_uid = items.filter(&uiid.eq(s_uiid)).load::<Item>(conn)?.pop();
+            // match the equivalent forms, e.g. isbn-10 of an isbn-13
+            let check_uid = items
+                .filter(lower(uiid).eq(any(uiid_keys(s_uiid))))
+                .load::<Item>(conn)?
+                .pop();
             if let Some(i) = check_uid {
-                return Ok(ItemMsg {
-                    status: 422,
-                    message: "Existing".to_string(),
-                    item: i,
-                });
+                return Err(ServiceError::Conflict(
+                    "Existing".into(),
+                    json!({ "field": "uiid", "id": i.id, "slug": i.slug }),
+                ));
             }
         }
 
         if s_url.trim() != "" {
             let check_url = items.filter(&url.eq(s_url)).load::<Item>(conn)?.pop();
             if let Some(i) = check_url {
-                return Ok(ItemMsg {
-                    status: 422,
-                    message: "Existing".to_string(),
-                    item: i,
-                });
+                return Err(ServiceError::Conflict(
+                    "Existing".into(),
+                    json!({ "field": "url", "id": i.id, "slug": i.slug }),
+                ));
             }
         }
 
@@ -89,10 +110,25 @@ impl Handler<UpdateItem> for Dba {
             old_item.clone().slug
         };
 
+        // a new url or cover is not checked yet, reset its status
+        let url_changed = item.url != old_item.url;
+        let cover_changed = item.cover != old_item.cover;
+        let url_st = if url_changed { 0 } else { old_item.url_status };
+        let cover_st = if cover_changed { 0 } else { old_item.cover_status };
+        let is_dead = (item.url != "" && is_dead_status(url_st))
+            || (item.cover != "" && is_dead_status(cover_st));
+        let check_at = if url_changed || cover_changed {
+            None
+        } else {
+            old_item.checked_at
+        };
+
+        let i_type = uiid_kind(&item.uiid);
         let item_update = diesel::update(&old_item)
             .set((
                 title.eq(item.title),
                 uiid.eq(item.uiid),
+                uiid_type.eq(i_type),
                 authors.eq(item.authors),
                 pub_at.eq(item.pub_at),
                 publisher.eq(item.publisher),
@@ -102,6 +138,12 @@ impl Handler<UpdateItem> for Dba {
                 edition.eq(item.edition),
                 detail.eq(item.detail),
                 slug.eq(i_slug),
+                (
+                    url_status.eq(url_st),
+                    cover_status.eq(cover_st),
+                    dead_link.eq(is_dead),
+                    checked_at.eq(check_at),
+                ),
             ))
             .get_result::<Item>(conn)?;
 
@@ -123,13 +165,32 @@ impl Handler<QueryItem> for Dba {
 
         let item_query = items
             .filter(&slug.eq(&islug.item_slug)) // slug here only
-            .get_result::<Item>(conn)?;
+            .load::<Item>(conn)?
+            .pop();
 
-        Ok(ItemMsg {
-            status: 200,
-            message: "Success".to_string(),
-            item: item_query,
-        })
+        match item_query {
+            Some(i) => Ok(ItemMsg {
+                status: 200,
+                message: "Success".to_string(),
+                item: i,
+            }),
+            None => {
+                // the slug of a merged item, redirect to the surviving one
+                use crate::schema::itemredirects::dsl::{itemredirects, old_slug};
+                let redirect = itemredirects
+                    .filter(&old_slug.eq(&islug.item_slug))
+                    .get_result::<ItemRedirect>(conn)?;
+                let item_to = items
+                    .filter(&id.eq(&redirect.item_id))
+                    .get_result::<Item>(conn)?;
+
+                Ok(ItemMsg {
+                    status: 301,
+                    message: "Moved".to_string(),
+                    item: item_to,
+                })
+            }
+        }
     }
 }
 
@@ -144,11 +205,20 @@ impl Handler<QueryItems> for Dba {
         let mut item_id_vec: Vec<String> = Vec::new();
         let mut item_list: Vec<Item> = Vec::new();
         let mut item_num = 0; // total
+        let mut next_cursor = "".to_string();
 
         // better do some limit
         match perid {
             QueryItems::ItemID(i) => {
                 item_list = items.filter(&id.eq(&i)).load::<Item>(conn)?;
+                if item_list.len() == 0 {
+                    // the id of a merged item
+                    use crate::schema::itemredirects::dsl::{item_id, itemredirects, old_id};
+                    item_id_vec = itemredirects
+                        .filter(&old_id.eq(&i))
+                        .select(item_id)
+                        .load::<String>(conn)?;
+                }
             }
             QueryItems::Title(t) => {
                 item_list = items
@@ -158,75 +228,70 @@ impl Handler<QueryItems> for Dba {
                     .load::<Item>(conn)?;
             }
             QueryItems::Uiid(d) => {
+                // also the id in any form, e.g. isbn-10 or doi url
+                let d_vars = normalize_uiid(&d).map(|u| uiid_keys(&u)).unwrap_or_default();
                 item_list = items
                     .filter(&uiid.ilike(&d))
+                    .or_filter(lower(uiid).eq(any(d_vars)))
                     .or_filter(&title.ilike(&d))
                     .limit(10)
                     .load::<Item>(conn)?;
             }
             QueryItems::ItemUrl(u) => {
-                // query in db or via spider
-                // url 1to1 item
-                let item = items.filter(&url.ilike(&u)).load::<Item>(conn)?.pop();
-                match item {
-                    Some(i) => {
-                        item_list = vec![i];
-                    }
-                    None => {
-                        item_list = Vec::new();
-                        // spider per url  // issue alert !!
-                        //println!("via spider");
-                        /* let page = WebPage::new(&u);
-                        let sp_item = page.into_item();
-                        // insert new to db
-                        let uuid_v4 = uuid::Uuid::new_v4();
-                        let uid = format!("{}", uuid_v4);
-                        let i_slug = gen_slug("i", &sp_item.title, &uuid_v4);
-                        let new_item = Item::new(uid, i_slug, sp_item);
-                        let item_new = diesel::insert_into(items)
-                            .values(&new_item)
-                            .get_result::<Item>(conn)?;
-                        item_list = vec![item_new]; */
-                    }
-                }
+                // query in db, url 1to1 item
+                // if miss, api crawles via Spider then submits, not block here
+                item_list = items.filter(&url.ilike(&u)).limit(1).load::<Item>(conn)?;
             }
             QueryItems::RutID(pid) => {
                 use crate::schema::collects::dsl::*;
                 item_id_vec = collects
                     .filter(&rut_id.eq(&pid)) // limit to 42 inserts, no need paging
+                    .order(item_order.asc())
                     .select(item_id)
                     .load::<String>(conn)?;
             }
-            QueryItems::TagID(pid) => {
-                use crate::schema::tagitems::dsl::*;
-                item_id_vec = tagitems
-                    .filter(&tname.eq(&pid))
-                    .order(count.desc())
-                    .limit(PER_PAGE.into()) // just limit most
-                    .select(item_id)
-                    .load::<String>(conn)?;
+            QueryItems::TagID(pid, sub, pg) => {
+                let mut tnames = vec![pid.clone()];
+                if sub {
+                    // include descendant tags, an item once even if tagged with several
+                    tnames.extend(tag_descendants(conn, &pid)?.into_iter().map(|tg| tg.tname));
+                }
+                let (num, ids, cursor) = tagged_page(conn, "item", &tnames, &pg)?;
+                item_num = num;
+                item_id_vec = ids;
+                next_cursor = cursor;
             }
-            QueryItems::UserID(pid, f, p) => {
-                use crate::schema::staritems::dsl::*;
-                let query = staritems.filter(uname.eq(pid)).filter(flag.eq(f));
-                item_num = query.clone().count().get_result(conn)?;
-                item_id_vec = if p < 1 {
-                    query
-                        .order(star_at.desc())
-                        .limit(10)
-                        .select(item_id)
-                        .load::<String>(conn)?
-                } else {
-                    query
-                        .order(star_at.desc())
-                        .limit(PER_PAGE.into())
-                        .offset((PER_PAGE * (p - 1)).into())
-                        .select(item_id)
-                        .load::<String>(conn)?
+            QueryItems::UserID(pid, f, pg) => {
+                use crate::schema::staritems::dsl::{
+                    flag, id as sid, item_id, star_at, staritems, uname,
                 };
+                item_num = staritems
+                    .filter(uname.eq(&pid))
+                    .filter(flag.eq(f))
+                    .count()
+                    .get_result(conn)?;
+                let mut query = staritems
+                    .filter(uname.eq(&pid))
+                    .filter(flag.eq(f))
+                    .select((item_id, star_at, sid))
+                    .into_boxed();
+                if pg.cursor != "" {
+                    let (c_at, c_id) = parse_time_cursor(&pg.cursor)?;
+                    query = query.filter(star_at.lt(c_at).or(star_at.eq(c_at).and(sid.lt(c_id))));
+                }
+                let stars = query
+                    .order((star_at.desc(), sid.desc()))
+                    .limit(pg.limit())
+                    .offset(pg.offset())
+                    .load::<(String, NaiveDateTime, String)>(conn)?;
+                if let Some(st) = stars.last().filter(|_| pg.is_full(stars.len())) {
+                    next_cursor = time_cursor(&st.1, &st.2);
+                }
+                item_id_vec = stars.into_iter().map(|st| st.0).collect();
             }
-            QueryItems::KeyID(k, f, i, p) => {
+            QueryItems::KeyID(k, f, i, pg) => {
                 // per keyword from taged, star
+                let mut query = items.filter(title.ilike(k)).into_boxed();
                 let fr = f.trim();
                 match fr {
                     "user" => {
@@ -236,12 +301,7 @@ impl Handler<QueryItems> for Dba {
                             .filter(&flag.eq(3)) // just search done
                             .select(item_id)
                             .load::<String>(conn)?;
-                        item_list = items
-                            .filter(&title.ilike(&k))
-                            .filter(&id.eq(any(&ids)))
-                            .order(rut_count.desc())
-                            .limit(PER_PAGE.into())
-                            .load::<Item>(conn)?;
+                        query = query.filter(id.eq(any(ids)));
                     }
                     "tag" => {
                         // hope never use, to optimaze
@@ -250,27 +310,32 @@ impl Handler<QueryItems> for Dba {
                             .filter(&tname.eq(&i))
                             .select(item_id)
                             .load::<String>(conn)?;
-                        item_list = items
-                            .filter(&title.ilike(&k))
-                            .filter(&id.eq(any(&ids)))
-                            .order(rut_count.desc())
-                            .limit(PER_PAGE.into())
-                            .load::<Item>(conn)?;
+                        query = query.filter(id.eq(any(ids)));
                     }
                     _ => {
                         // just query per keyword, hope never use
-                        item_list = items
-                            .filter(&title.ilike(&k))
-                            .order(rut_count.desc())
-                            .limit(PER_PAGE.into())
-                            .load::<Item>(conn)?;
                     }
                 }
+                // popular first, keyset per (rut_count, id)
+                if pg.cursor != "" {
+                    let (c_n, c_id) = parse_int_cursor::<i32>(&pg.cursor)?;
+                    query = query
+                        .filter(rut_count.lt(c_n).or(rut_count.eq(c_n).and(id.lt(c_id))));
+                }
+                item_list = query
+                    .order((rut_count.desc(), id.desc()))
+                    .limit(pg.limit())
+                    .offset(pg.offset())
+                    .load::<Item>(conn)?;
+                if let Some(it) = item_list.last().filter(|_| pg.is_full(item_list.len())) {
+                    next_cursor = encode_cursor(&it.rut_count.to_string(), &it.id);
+                }
             }
         };
 
         if item_id_vec.len() > 0 {
             let mut items_query = items.filter(&id.eq(any(&item_id_vec))).load::<Item>(conn)?;
+            sort_by_ids(&mut items_query, &item_id_vec, |i| &i.id);
             item_list.append(&mut items_query);
         }
 
@@ -285,6 +350,7 @@ impl Handler<QueryItems> for Dba {
             message: "Success".to_string(),
             items: item_list,
             count: item_count,
+            next_cursor,
         })
     }
 }
@@ -299,58 +365,64 @@ impl Handler<CollectItem> for Dba {
         use crate::schema::ruts::dsl::{id as rid, item_count, logo, renew_at, ruts};
         let conn = &self.0.get()?;
 
-        // to check if have collected
-        let check_collect = collects
-            .filter(&rut_id.eq(&collect.rut_id))
-            .filter(&item_id.eq(&collect.item_id))
-            .load::<Collect>(conn)?
-            .pop();
-        if let Some(c) = check_collect {
-            return Err(ServiceError::BadRequest("400: Duplicate".into()));
-        }
-
-        // get item cover then as rut logo, and check if item exist
-        let item_q = items
-            .filter(&itemid.eq(&collect.item_id))
-            .get_result::<Item>(conn)?;
-
-        // to gen item order, curr_item_count + 1, or pass from frontend
-        let rutID = collect.clone().rut_id;
-        let rut_q = ruts //query once for select/update
-            .filter(&rid.eq(&rutID))
-            .get_result::<Rut>(conn)?;
-        let item_num = (&rut_q).item_count;
-        // limit the item_count to 42
-        if item_num >= 42 {
-            return Err(ServiceError::BadRequest("418: Answer 42".into()));
-        }
-
-        // new collect
-        let uuid_v4 = uuid::Uuid::new_v4();
-        let uid = format!("{}", uuid_v4);
-        let i_order = (item_num + 1) as i16;
-        let new_collect = Collect::new(uid, i_order, collect);
-        let collect_new = diesel::insert_into(collects)
-            .values(&new_collect)
-            .get_result::<Collect>(conn)?;
+        conn.transaction::<_, ServiceError, _>(|| {
+            // to check if have collected
+            let check_collect = collects
+                .filter(&rut_id.eq(&collect.rut_id))
+                .filter(&item_id.eq(&collect.item_id))
+                .load::<Collect>(conn)?
+                .pop();
+            if let Some(c) = check_collect {
+                return Err(ServiceError::Conflict(
+                    "Collected".into(),
+                    json!({ "field": "item_id", "id": c.id }),
+                ));
+            }
 
-        // to update the item_count + 1 and logo and renew_at in rut
-        diesel::update(&rut_q)
-            .set((
-                item_count.eq(item_count + 1),
-                logo.eq(&item_q.cover),
-                renew_at.eq(Utc::now().naive_utc()),
-            ))
-            .execute(conn)?;
-        // to update the rut_count + 1 in item
-        diesel::update(&item_q)
-            .set(rut_count.eq(rut_count + 1))
-            .execute(conn)?;
+            // get item cover then as rut logo, and check if item exist
+            let item_q = items
+                .filter(&itemid.eq(&collect.item_id))
+                .get_result::<Item>(conn)?;
+
+            // to gen item order, curr_item_count + 1, or pass from frontend
+            let rutID = collect.clone().rut_id;
+            let rut_q = ruts //query once for select/update
+                .filter(&rid.eq(&rutID))
+                .get_result::<Rut>(conn)?;
+            let item_num = (&rut_q).item_count;
+            // limit the item_count to 42
+            if item_num >= 42 {
+                return Err(ServiceError::BadRequest("418: Answer 42".into()));
+            }
 
-        Ok(CollectMsg {
-            status: 201,
-            message: "Collected".to_string(),
-            collect: collect_new,
+            // new collect
+            let uuid_v4 = uuid::Uuid::new_v4();
+            let uid = format!("{}", uuid_v4);
+            let i_order = (item_num + 1) as i16;
+            let new_collect = Collect::new(uid, i_order, collect);
+            let collect_new = diesel::insert_into(collects)
+                .values(&new_collect)
+                .get_result::<Collect>(conn)?;
+
+            // to update the item_count + 1 and logo and renew_at in rut
+            diesel::update(&rut_q)
+                .set((
+                    item_count.eq(item_count + 1),
+                    logo.eq(&item_q.cover),
+                    renew_at.eq(Utc::now().naive_utc()),
+                ))
+                .execute(conn)?;
+            // to update the rut_count + 1 in item
+            diesel::update(&item_q)
+                .set(rut_count.eq(rut_count + 1))
+                .execute(conn)?;
+            add_timeline(conn, &collect_new.uname, "collect", "item", &collect_new.item_id)?;
+
+            Ok(CollectMsg {
+                status: 201,
+                message: "Collected".to_string(),
+                collect: collect_new,
+            })
         })
     }
 }
@@ -366,8 +438,11 @@ impl Handler<UpdateCollect> for Dba {
         let collect_query = collects
             .filter(&id.eq(&up_collect.id))
             .get_result::<Collect>(conn)?;
-        if collect_query.uname != up_collect.uname {
-            return Err(ServiceError::Unauthorized);
+        // check permission: owner or editor
+        if collect_query.uname != up_collect.uname
+            && !user_can(conn, &up_collect.uname, EIDT_PERMIT)?
+        {
+            return Err(ServiceError::Forbidden);
         }
 
         let collect_update = diesel::update(&collect_query)
@@ -390,57 +465,126 @@ impl Handler<DelCollect> for Dba {
         use crate::schema::collects::dsl::*;
         let conn = &self.0.get()?;
 
-        let q_collect = collects
-            .filter(&id.eq(&dc.collect_id))
-            .get_result::<Collect>(conn)?;
+        conn.transaction::<_, ServiceError, _>(|| {
+            let q_collect = collects
+                .filter(&id.eq(&dc.collect_id))
+                .get_result::<Collect>(conn)?;
 
-        let query_c = q_collect.clone();
+            let query_c = q_collect.clone();
 
-        // check permission
-        if dc.uname != query_c.uname {
-            return Err(ServiceError::Unauthorized);
-        }
-        // some var to use in re-order
-        let order_del = query_c.item_order;
-        let rutID = query_c.rut_id;
-        let itemID = query_c.item_id;
+            // check permission: owner or editor
+            if dc.uname != query_c.uname && !user_can(conn, &dc.uname, EIDT_PERMIT)? {
+                return Err(ServiceError::Forbidden);
+            }
+            // some var to use in re-order
+            let order_del = query_c.item_order;
+            let rutID = query_c.rut_id;
+            let itemID = query_c.item_id;
+
+            // perform deletion
+            diesel::delete(&q_collect).execute(conn)?;
+
+            // to update the item_count - 1 and renew_at in rut
+            use crate::schema::ruts::dsl::{id as rid, item_count, renew_at, ruts};
+            let rut_q = ruts.filter(&rid.eq(&rutID)).get_result::<Rut>(conn)?;
+
+            let item_num = rut_q.item_count as i16; // to use in re-order
+
+            diesel::update(&rut_q)
+                .set((
+                    item_count.eq(item_count - 1),
+                    renew_at.eq(Utc::now().naive_utc()),
+                ))
+                .execute(conn)?;
+            // to update the rut_count - 1 in item
+            use crate::schema::items::dsl::{id as itemid, items, rut_count};
+            diesel::update(items.filter(&itemid.eq(&itemID)))
+                .set(rut_count.eq(rut_count - 1))
+                .execute(conn)?;
+            // to update the item order of collect IF not del last one
+            if item_num > order_del {
+                let lower = order_del + 1;
+                let upper = item_num;
+                diesel::update(
+                    collects
+                        .filter(rut_id.eq(rutID))
+                        .filter(item_order.between(lower, upper)), // betw, inclusive
+                )
+                .set(item_order.eq(item_order - 1))
+                .execute(conn)?;
+            }
+
+            Ok(Msg {
+                status: 200,
+                message: "Deleted".to_string(),
+            })
+        })
+    }
+}
+
+// handle msg from api::item.reorder_collect
+impl Handler<ReorderCollect> for Dba {
+    type Result = Result<CollectsMsg, ServiceError>;
 
-        // perform deletion
-        diesel::delete(&q_collect).execute(conn)?;
+    fn handle(&mut self, ro: ReorderCollect, _: &mut Self::Context) -> Self::Result {
+        use crate::schema::collects::dsl::*;
+        let conn = &self.0.get()?;
 
-        // to update the item_count - 1 and renew_at in rut
-        use crate::schema::ruts::dsl::{id as rid, item_count, renew_at, ruts};
-        let rut_q = ruts.filter(&rid.eq(&rutID)).get_result::<Rut>(conn)?;
+        conn.transaction::<_, ServiceError, _>(|| {
+            use crate::schema::ruts::dsl::{id as rid, renew_at, ruts};
+            let rut_q = ruts.filter(&rid.eq(&ro.rut_id)).get_result::<Rut>(conn)?;
 
-        let item_num = rut_q.item_count as i16; // to use in re-order
+            // check permission, only the rut owner can re-order
+            if ro.uname != rut_q.uname {
+                return Err(ServiceError::Forbidden);
+            }
 
-        diesel::update(&rut_q)
-            .set((
-                item_count.eq(item_count - 1),
-                renew_at.eq(Utc::now().naive_utc()),
-            ))
-            .execute(conn)?;
-        // to update the rut_count - 1 in item
-        use crate::schema::items::dsl::{id as itemid, items, rut_count};
-        diesel::update(items.filter(&itemid.eq(&itemID)))
-            .set(rut_count.eq(rut_count - 1))
-            .execute(conn)?;
-        // to update the item order of collect IF not del last one
-        if item_num > order_del {
-            let lower = order_del + 1;
-            let upper = item_num;
-            diesel::update(
-                collects
-                    .filter(rut_id.eq(rutID))
-                    .filter(item_order.between(lower, upper)), // betw, inclusive
-            )
-            .set(item_order.eq(item_order - 1))
-            .execute(conn)?;
-        }
+            let q_collect = collects
+                .filter(&id.eq(&ro.collect_id))
+                .filter(&rut_id.eq(&ro.rut_id))
+                .get_result::<Collect>(conn)?;
 
-        Ok(Msg {
-            status: 204,
-            message: "Deleted".to_string(),
+            let old_order = q_collect.item_order;
+            let item_num = rut_q.item_count as i16;
+            let new_order = std::cmp::min(std::cmp::max(ro.item_order, 1), item_num);
+
+            // shift the collects between old and new order
+            if new_order < old_order {
+                diesel::update(
+                    collects
+                        .filter(rut_id.eq(&ro.rut_id))
+                        .filter(item_order.between(new_order, old_order - 1)),
+                )
+                .set(item_order.eq(item_order + 1))
+                .execute(conn)?;
+            } else if new_order > old_order {
+                diesel::update(
+                    collects
+                        .filter(rut_id.eq(&ro.rut_id))
+                        .filter(item_order.between(old_order + 1, new_order)),
+                )
+                .set(item_order.eq(item_order - 1))
+                .execute(conn)?;
+            }
+
+            diesel::update(&q_collect)
+                .set(item_order.eq(new_order))
+                .execute(conn)?;
+            diesel::update(&rut_q)
+                .set(renew_at.eq(Utc::now().naive_utc()))
+                .execute(conn)?;
+
+            let collect_list = collects
+                .filter(&rut_id.eq(&ro.rut_id))
+                .order(item_order.asc())
+                .load::<Collect>(conn)?;
+
+            Ok(CollectsMsg {
+                status: 200,
+                message: "Reordered".to_string(),
+                collects: collect_list,
+                next_cursor: "".to_string(),
+            })
         })
     }
 }
@@ -454,35 +598,45 @@ impl Handler<QueryCollects> for Dba {
         let conn = &self.0.get()?;
 
         let mut collect_list: Vec<Collect> = Vec::new();
+        let mut next_cursor = "".to_string();
         match cid {
             QueryCollects::RutID(r) => {
-                collect_list = collects.filter(&rut_id.eq(&r)).load::<Collect>(conn)?;
+                collect_list = collects
+                    .filter(&rut_id.eq(&r))
+                    .order(item_order.asc())
+                    .load::<Collect>(conn)?;
             }
-            QueryCollects::ItemID(i, p) => {
-                collect_list = if p < 1 {
-                    // no limit
-                    collects.filter(&item_id.eq(&i)).load::<Collect>(conn)?
-                } else {
-                    collects
-                        .filter(&item_id.eq(&i))
-                        .order(collect_at.desc())
-                        .limit(PER_PAGE.into())
-                        .offset((PER_PAGE * (p - 1)).into())
-                        .load::<Collect>(conn)?
-                };
+            QueryCollects::ItemID(i, pg) => {
+                let mut query = collects.filter(item_id.eq(i)).into_boxed();
+                if pg.cursor != "" {
+                    let (c_at, c_id) = parse_time_cursor(&pg.cursor)?;
+                    query = query
+                        .filter(collect_at.lt(c_at).or(collect_at.eq(c_at).and(id.lt(c_id))));
+                }
+                collect_list = query
+                    .order((collect_at.desc(), id.desc()))
+                    .limit(pg.limit())
+                    .offset(pg.offset())
+                    .load::<Collect>(conn)?;
+                if let Some(c) = collect_list.last().filter(|_| pg.is_full(collect_list.len())) {
+                    next_cursor = time_cursor(&c.collect_at, &c.id);
+                }
             }
-            QueryCollects::UserID(u, p) => {
-                collect_list = if p < 1 {
-                    // no limit
-                    collects.filter(&uname.eq(&u)).load::<Collect>(conn)?
-                } else {
-                    collects
-                        .filter(&uname.eq(&u))
-                        .order(collect_at.desc())
-                        .limit(PER_PAGE.into())
-                        .offset((PER_PAGE * (p - 1)).into())
-                        .load::<Collect>(conn)?
-                };
+            QueryCollects::UserID(u, pg) => {
+                let mut query = collects.filter(uname.eq(u)).into_boxed();
+                if pg.cursor != "" {
+                    let (c_at, c_id) = parse_time_cursor(&pg.cursor)?;
+                    query = query
+                        .filter(collect_at.lt(c_at).or(collect_at.eq(c_at).and(id.lt(c_id))));
+                }
+                collect_list = query
+                    .order((collect_at.desc(), id.desc()))
+                    .limit(pg.limit())
+                    .offset(pg.offset())
+                    .load::<Collect>(conn)?;
+                if let Some(c) = collect_list.last().filter(|_| pg.is_full(collect_list.len())) {
+                    next_cursor = time_cursor(&c.collect_at, &c.id);
+                }
             }
         }
 
@@ -490,6 +644,7 @@ impl Handler<QueryCollects> for Dba {
             status: 200,
             message: "Get".to_string(),
             collects: collect_list,
+            next_cursor,
         })
     }
 }
@@ -522,51 +677,62 @@ impl Handler<NewStarItem> for Dba {
         use crate::schema::staritems::dsl::*;
         let conn = &self.0.get()?;
 
-        // check if star-ed already
-        let check_star = staritems
-            .filter(&uname.eq(&istar.uname))
-            .filter(&item_id.eq(&istar.item_id))
-            .load::<StarItem>(conn)?
-            .pop();
+        conn.transaction::<_, ServiceError, _>(|| {
+            // check if star-ed already
+            let check_star = staritems
+                .filter(&uname.eq(&istar.uname))
+                .filter(&item_id.eq(&istar.item_id))
+                .load::<StarItem>(conn)?
+                .pop();
+
+            // flag
+            let flg = istar.flag;
+            let mut si: StarItem;
+
+            // log only on a new star or a flag change, not on note or rate edit
+            let flag_changed = match &check_star {
+                Some(s) => s.flag != flg,
+                None => true,
+            };
 
-        // flag
-        let flg = istar.flag;
-        let mut si: StarItem;
-
-        if let Some(s) = check_star {
-            // if stared, just update flag:  todo -> doing -> done
-            si = diesel::update(&s)
-                .set((note.eq(&istar.note), flag.eq(&flg), rate.eq(&istar.rate)))
-                .get_result::<StarItem>(conn)?;
-            // update item done_count + 1 if done
-            if flg == 3 {
-                use crate::schema::items::dsl::{done_count, id as itemid, items};
-                diesel::update(items.filter(&itemid.eq(&istar.item_id)))
-                    .set(done_count.eq(done_count + 1))
-                    .execute(conn)?;
+            if let Some(s) = check_star {
+                // if stared, just update flag:  todo -> doing -> done
+                si = diesel::update(&s)
+                    .set((note.eq(&istar.note), flag.eq(&flg), rate.eq(&istar.rate)))
+                    .get_result::<StarItem>(conn)?;
+                // update item done_count + 1 if turn to done
+                if flg == 3 && s.flag != 3 {
+                    use crate::schema::items::dsl::{done_count, id as itemid, items};
+                    diesel::update(items.filter(&itemid.eq(&istar.item_id)))
+                        .set(done_count.eq(done_count + 1))
+                        .execute(conn)?;
+                }
+            } else {
+                // otherwise new star-item
+                let uid = format!("{}", uuid::Uuid::new_v4());
+                let new_star = StarItem {
+                    id: uid,
+                    uname: istar.uname,
+                    item_id: istar.item_id,
+                    star_at: Utc::now().naive_utc(),
+                    note: istar.note,
+                    flag: flg,
+                    rate: istar.rate,
+                };
+                si = diesel::insert_into(staritems)
+                    .values(&new_star)
+                    .get_result::<StarItem>(conn)?;
+            }
+            if flag_changed {
+                add_timeline(conn, &si.uname, "star", "item", &si.item_id)?;
             }
-        } else {
-            // otherwise new star-item
-            let uid = format!("{}", uuid::Uuid::new_v4());
-            let new_star = StarItem {
-                id: uid,
-                uname: istar.uname,
-                item_id: istar.item_id,
-                star_at: Utc::now().naive_utc(),
-                note: istar.note,
-                flag: flg,
-                rate: istar.rate,
-            };
-            si = diesel::insert_into(staritems)
-                .values(&new_star)
-                .get_result::<StarItem>(conn)?;
-        }
 
-        Ok(StarItemMsg {
-            status: 200,
-            message: si.flag.to_string(),
-            note: si.note,
-            when: si.star_at.to_string(),
+            Ok(StarItemMsg {
+                status: 200,
+                message: si.flag.to_string(),
+                note: si.note,
+                when: si.star_at.to_string(),
+            })
         })
     }
 }
@@ -603,3 +769,883 @@ impl Handler<StarItemStatus> for Dba {
         }
     }
 }
+
+// handle msg from api::item.merge
+impl Handler<MergeItem> for Dba {
+    type Result = Result<ItemMsg, ServiceError>;
+
+    fn handle(&mut self, mg: MergeItem, _: &mut Self::Context) -> Self::Result {
+        use crate::schema::items::dsl::*;
+        let conn = &self.0.get()?;
+
+        conn.transaction::<_, ServiceError, _>(|| {
+            let from_item = items.filter(&id.eq(&mg.from)).get_result::<Item>(conn)?;
+            let to_item = items.filter(&id.eq(&mg.to)).get_result::<Item>(conn)?;
+
+            // move collects, if a rut collected both, drop the from one and re-order
+            {
+                use crate::schema::collects::dsl::{collects, item_id, item_order, rut_id};
+                use crate::schema::ruts::dsl::{id as rid, item_count, renew_at, ruts};
+                for c in collects.filter(&item_id.eq(&mg.from)).load::<Collect>(conn)? {
+                    let to_c = collects
+                        .filter(&rut_id.eq(&c.rut_id))
+                        .filter(&item_id.eq(&mg.to))
+                        .load::<Collect>(conn)?
+                        .pop();
+                    match to_c {
+                        Some(t) => {
+                            if t.content.trim() == "" && c.content.trim() != "" {
+                                diesel::update(&t)
+                                    .set(crate::schema::collects::dsl::content.eq(&c.content))
+                                    .execute(conn)?;
+                            }
+                            diesel::delete(&c).execute(conn)?;
+                            diesel::update(
+                                collects
+                                    .filter(&rut_id.eq(&c.rut_id))
+                                    .filter(item_order.gt(c.item_order)),
+                            )
+                            .set(item_order.eq(item_order - 1))
+                            .execute(conn)?;
+                            diesel::update(ruts.filter(&rid.eq(&c.rut_id)))
+                                .set((
+                                    item_count.eq(item_count - 1),
+                                    renew_at.eq(Utc::now().naive_utc()),
+                                ))
+                                .execute(conn)?;
+                        }
+                        None => {
+                            diesel::update(&c).set(item_id.eq(&mg.to)).execute(conn)?;
+                        }
+                    }
+                }
+            }
+            // move staritems, if starred both, keep the further flag: todo < doing < done
+            {
+                use crate::schema::staritems::dsl::{flag, item_id, note, rate, staritems, uname};
+                for st in staritems.filter(&item_id.eq(&mg.from)).load::<StarItem>(conn)? {
+                    let to_st = staritems
+                        .filter(&uname.eq(&st.uname))
+                        .filter(&item_id.eq(&mg.to))
+                        .load::<StarItem>(conn)?
+                        .pop();
+                    match to_st {
+                        Some(t) => {
+                            if st.flag > t.flag {
+                                diesel::update(&t)
+                                    .set((flag.eq(st.flag), rate.eq(st.rate), note.eq(&st.note)))
+                                    .execute(conn)?;
+                            }
+                            diesel::delete(&st).execute(conn)?;
+                        }
+                        None => {
+                            diesel::update(&st).set(item_id.eq(&mg.to)).execute(conn)?;
+                        }
+                    }
+                }
+            }
+            // move tagitems, if tagged both, combine count
+            {
+                use crate::schema::tagitems::dsl::{count, id as tid, item_id, tagitems, tname};
+                for ti in tagitems.filter(&item_id.eq(&mg.from)).load::<TagItem>(conn)? {
+                    let to_ti = tagitems
+                        .filter(&tname.eq(&ti.tname))
+                        .filter(&item_id.eq(&mg.to))
+                        .load::<TagItem>(conn)?
+                        .pop();
+                    match to_ti {
+                        Some(t) => {
+                            diesel::update(&t)
+                                .set(count.eq(count + ti.count))
+                                .execute(conn)?;
+                            diesel::delete(&ti).execute(conn)?;
+                            untag_upkeep(conn, &ti.tname, "item")?;
+                        }
+                        None => {
+                            diesel::update(&ti)
+                                .set((
+                                    tid.eq(format!("{}-{}", ti.tname, mg.to)),
+                                    item_id.eq(&mg.to),
+                                ))
+                                .execute(conn)?;
+                        }
+                    }
+                }
+            }
+            // move etcs on item
+            {
+                use crate::schema::etcs::dsl::{etcs, item_id};
+                diesel::update(etcs.filter(&item_id.eq(&mg.from)))
+                    .set(item_id.eq(&mg.to))
+                    .execute(conn)?;
+            }
+            // move activities on item
+            {
+                use crate::schema::timelines::dsl::{obj, objid, timelines};
+                diesel::update(timelines.filter(&obj.eq("item")).filter(&objid.eq(&mg.from)))
+                    .set(objid.eq(&mg.to))
+                    .execute(conn)?;
+            }
+
+            // re-count per relations
+            let r_count: i64 = {
+                use crate::schema::collects::dsl::{collects, item_id};
+                collects.filter(&item_id.eq(&mg.to)).count().get_result(conn)?
+            };
+            let d_count: i64 = {
+                use crate::schema::staritems::dsl::{flag, item_id, staritems};
+                staritems
+                    .filter(&item_id.eq(&mg.to))
+                    .filter(&flag.eq(3))
+                    .count()
+                    .get_result(conn)?
+            };
+            let e_count: i64 = {
+                use crate::schema::etcs::dsl::{etcs, item_id};
+                etcs.filter(&item_id.eq(&mg.to)).count().get_result(conn)?
+            };
+            // keep the identifiers of from for lookup, if to has none
+            let pick = |t: &str, f: &str| if t.trim() == "" { f.to_owned() } else { t.to_owned() };
+            let m_uiid = pick(&to_item.uiid, &from_item.uiid);
+            let item_merged = diesel::update(&to_item)
+                .set((
+                    uiid_type.eq(uiid_kind(&m_uiid)),
+                    uiid.eq(m_uiid),
+                    url.eq(pick(&to_item.url, &from_item.url)),
+                    cover.eq(pick(&to_item.cover, &from_item.cover)),
+                    rut_count.eq(r_count as i32),
+                    done_count.eq(d_count as i32),
+                    etc_count.eq(e_count as i32),
+                ))
+                .get_result::<Item>(conn)?;
+
+            // drop from, leave a redirect of its slug
+            diesel::delete(&from_item).execute(conn)?;
+            {
+                use crate::schema::itemredirects::dsl::{item_id, itemredirects};
+                diesel::update(itemredirects.filter(&item_id.eq(&mg.from)))
+                    .set(item_id.eq(&mg.to))
+                    .execute(conn)?;
+                let redirect = ItemRedirect::new(&from_item, mg.to, mg.uname);
+                diesel::insert_into(itemredirects)
+                    .values(&redirect)
+                    .execute(conn)?;
+            }
+
+            Ok(ItemMsg {
+                status: 200,
+                message: "Merged".to_string(),
+                item: item_merged,
+            })
+        })
+    }
+}
+
+// likely duplicate items, grouped per normalized key
+// $1: limit, $2: offset
+const DUP_ITEMS_SQL: &str = "
+    SELECT d.per, d.dkey,
+        array_agg(d.id ORDER BY d.rut_count DESC) AS ids,
+        array_agg(d.title ORDER BY d.rut_count DESC) AS titles,
+        array_agg(d.slug ORDER BY d.rut_count DESC) AS slugs,
+        COUNT(*) AS count
+    FROM (
+        SELECT 'uiid' AS per, regexp_replace(lower(i.uiid), '[^a-z0-9]', '', 'g') AS dkey,
+            i.id, i.title, i.slug, i.rut_count
+        FROM items i
+        UNION ALL
+        SELECT 'title' AS per, regexp_replace(lower(i.title || i.authors), '[^a-z0-9]', '', 'g') AS dkey,
+            i.id, i.title, i.slug, i.rut_count
+        FROM items i
+    ) d
+    WHERE d.dkey <> ''
+    GROUP BY d.per, d.dkey
+    HAVING COUNT(*) > 1
+    ORDER BY count DESC, d.dkey
+    LIMIT $1 OFFSET $2
+";
+
+// handle msg from api::item.get_dups
+impl Handler<QueryDupItems> for Dba {
+    type Result = Result<DupItemsMsg, ServiceError>;
+
+    fn handle(&mut self, qd: QueryDupItems, _: &mut Self::Context) -> Self::Result {
+        let conn = &self.0.get()?;
+
+        let pg = qd.paging;
+        let dup_list = sql_query(DUP_ITEMS_SQL)
+            .bind::<BigInt, _>(pg.limit())
+            .bind::<BigInt, _>(pg.offset())
+            .load::<DupItems>(conn)?;
+
+        Ok(DupItemsMsg {
+            status: 200,
+            message: "Success".to_string(),
+            count: dup_list.len(),
+            dups: dup_list,
+        })
+    }
+}
+
+// handle msg from bot::linkcheck, never checked first, then the stale
+impl Handler<QueryLinkCheck> for Dba {
+    type Result = Result<Vec<Item>, ServiceError>;
+
+    fn handle(&mut self, lc: QueryLinkCheck, _: &mut Self::Context) -> Self::Result {
+        use crate::schema::items::dsl::*;
+        let conn = &self.0.get()?;
+
+        let mut item_list = items
+            .filter(url.ne("").or(cover.ne("")))
+            .filter(checked_at.is_null())
+            .order(id.asc())
+            .limit(lc.limit)
+            .load::<Item>(conn)?;
+        let more = lc.limit - item_list.len() as i64;
+        if more > 0 {
+            let due_at = Utc::now().naive_utc() - Duration::days(LINK_CHECK_AGE);
+            let mut stale = items
+                .filter(url.ne("").or(cover.ne("")))
+                .filter(checked_at.lt(due_at))
+                .order(checked_at.asc())
+                .limit(more)
+                .load::<Item>(conn)?;
+            item_list.append(&mut stale);
+        }
+
+        Ok(item_list)
+    }
+}
+
+// handle msg from bot::linkcheck, record status, suggest but not overwrite
+impl Handler<SaveLinkCheck> for Dba {
+    type Result = Result<(), ServiceError>;
+
+    fn handle(&mut self, lc: SaveLinkCheck, _: &mut Self::Context) -> Self::Result {
+        use crate::schema::items::dsl::*;
+        let conn = &self.0.get()?;
+
+        conn.transaction::<_, ServiceError, _>(|| {
+            let item = items.filter(&id.eq(&lc.item_id)).get_result::<Item>(conn)?;
+            // 0: not checked this time, keep the last
+            let url_st = if lc.url_status == 0 { item.url_status } else { lc.url_status };
+            let cover_st = if lc.cover_status == 0 { item.cover_status } else { lc.cover_status };
+            let cover_dead = item.cover != "" && is_dead_status(cover_st);
+            let is_dead = (item.url != "" && is_dead_status(url_st)) || cover_dead;
+
+            diesel::update(&item)
+                .set((
+                    url_status.eq(url_st),
+                    cover_status.eq(cover_st),
+                    dead_link.eq(is_dead),
+                    checked_at.eq(Utc::now().naive_utc()),
+                ))
+                .execute(conn)?;
+
+            // suggest per draft: fill the empty field, or replace the dead cover
+            if let Some(d) = lc.draft {
+                use crate::schema::itemsuggests::dsl::{
+                    field, item_id, itemsuggests, new_value, status,
+                };
+                let fields = vec![
+                    ("uiid", &item.uiid, &d.uiid),
+                    ("authors", &item.authors, &d.authors),
+                    ("pub_at", &item.pub_at, &d.pub_at),
+                    ("publisher", &item.publisher, &d.publisher),
+                    ("edition", &item.edition, &d.edition),
+                    ("cover", &item.cover, &d.cover),
+                ];
+                for (f, old, new) in fields {
+                    let (old, new) = (old.trim(), new.trim());
+                    let to_suggest = new != ""
+                        && new != old
+                        && (old == "" || (f == "cover" && cover_dead));
+                    if !to_suggest {
+                        continue;
+                    }
+                    let pending: i64 = itemsuggests
+                        .filter(item_id.eq(&item.id))
+                        .filter(field.eq(f))
+                        .filter(new_value.eq(new))
+                        .filter(status.eq("pending"))
+                        .count()
+                        .get_result(conn)?;
+                    if pending == 0 {
+                        diesel::insert_into(itemsuggests)
+                            .values(&ItemSuggest::new(&item.id, f, old, new))
+                            .execute(conn)?;
+                    }
+                }
+            }
+
+            Ok(())
+        })
+    }
+}
+
+// handle msg from api::item.get_suggests
+impl Handler<QuerySuggests> for Dba {
+    type Result = Result<SuggestListMsg, ServiceError>;
+
+    fn handle(&mut self, qs: QuerySuggests, _: &mut Self::Context) -> Self::Result {
+        use crate::schema::itemsuggests::dsl::*;
+        let conn = &self.0.get()?;
+
+        let pg = qs.paging;
+        let mut query = itemsuggests.filter(status.eq(&qs.status)).into_boxed();
+        // keyset paging per (create_at, id) if cursor, else offset per page
+        if pg.cursor != "" {
+            let (c_at, c_id) = parse_time_cursor(&pg.cursor)?;
+            query = query.filter(create_at.lt(c_at).or(create_at.eq(c_at).and(id.lt(c_id))));
+        }
+        let suggest_list = query
+            .order((create_at.desc(), id.desc()))
+            .limit(pg.limit())
+            .offset(pg.offset())
+            .load::<ItemSuggest>(conn)?;
+
+        let next_cursor = match suggest_list.last() {
+            Some(s) if pg.is_full(suggest_list.len()) => time_cursor(&s.create_at, &s.id),
+            _ => "".to_string(),
+        };
+
+        Ok(SuggestListMsg {
+            status: 200,
+            message: "Success".to_string(),
+            count: suggest_list.len(),
+            suggests: suggest_list,
+            next_cursor,
+        })
+    }
+}
+
+// handle msg from api::item.decide_suggest
+impl Handler<DecideSuggest> for Dba {
+    type Result = Result<ItemMsg, ServiceError>;
+
+    fn handle(&mut self, ds: DecideSuggest, _: &mut Self::Context) -> Self::Result {
+        use crate::schema::items::dsl::*;
+        let conn = &self.0.get()?;
+
+        conn.transaction::<_, ServiceError, _>(|| {
+            use crate::schema::itemsuggests::dsl::{decide_at, itemsuggests, status, uname};
+            let sg = itemsuggests
+                .filter(crate::schema::itemsuggests::dsl::id.eq(&ds.id))
+                .get_result::<ItemSuggest>(conn)?;
+            if sg.status != "pending" {
+                return Err(ServiceError::BadRequest("Decided".into()));
+            }
+
+            let item_q = items.filter(&id.eq(&sg.item_id)).get_result::<Item>(conn)?;
+            let v = &sg.new_value;
+            let item_up = if ds.action == 1 {
+                let up = diesel::update(&item_q);
+                match sg.field.as_str() {
+                    "uiid" => up
+                        .set((uiid.eq(v), uiid_type.eq(uiid_kind(v))))
+                        .get_result::<Item>(conn)?,
+                    "authors" => up.set(authors.eq(v)).get_result::<Item>(conn)?,
+                    "pub_at" => up.set(pub_at.eq(v)).get_result::<Item>(conn)?,
+                    "publisher" => up.set(publisher.eq(v)).get_result::<Item>(conn)?,
+                    "edition" => up.set(edition.eq(v)).get_result::<Item>(conn)?,
+                    "cover" => {
+                        // new cover unchecked, dead per url only
+                        let url_dead = item_q.url != "" && is_dead_status(item_q.url_status);
+                        up.set((cover.eq(v), cover_status.eq(0), dead_link.eq(url_dead)))
+                            .get_result::<Item>(conn)?
+                    }
+                    _ => return Err(ServiceError::BadRequest("Invalid Field".into())),
+                }
+            } else {
+                item_q
+            };
+
+            let decision = if ds.action == 1 { "approved" } else { "rejected" };
+            diesel::update(&sg)
+                .set((
+                    status.eq(decision),
+                    uname.eq(&ds.uname),
+                    decide_at.eq(Utc::now().naive_utc()),
+                ))
+                .execute(conn)?;
+
+            Ok(ItemMsg {
+                status: 200,
+                message: decision.to_string(),
+                item: item_up,
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_util::{ago, seed_item, seed_rut, seed_tag, uid, TestDb};
+    use crate::model::Paging;
+
+    // fail on the last counter update, after the collect insert and rut update
+    #[test]
+    #[ignore]
+    fn collect_item_rollback_on_failure() {
+        use crate::schema::collects::dsl::{collects, rut_id};
+        use crate::schema::items::dsl::{id as itemid, items, rut_count};
+        use crate::schema::ruts::dsl::{id as rid, item_count, ruts};
+
+        let mut db = TestDb::new();
+        let uname = uid();
+        let (rut, item) = {
+            let conn = db.conn();
+            let rut = seed_rut(&conn, &uname);
+            let item = seed_item(&conn);
+            // int4 overflow on rut_count + 1
+            diesel::update(items.filter(itemid.eq(&item.id)))
+                .set(rut_count.eq(std::i32::MAX))
+                .execute(&*conn)
+                .unwrap();
+            (rut, item)
+        };
+
+        let res = db.send(CollectItem {
+            rut_id: rut.id.clone(),
+            item_id: item.id.clone(),
+            item_order: 1,
+            content: "".to_owned(),
+            uname: uname.clone(),
+        });
+        assert!(res.is_err());
+
+        let conn = db.conn();
+        let c_num: i64 = collects
+            .filter(rut_id.eq(&rut.id))
+            .count()
+            .get_result(&*conn)
+            .unwrap();
+        assert_eq!(c_num, 0);
+        let i_num: i32 = ruts
+            .filter(rid.eq(&rut.id))
+            .select(item_count)
+            .get_result(&*conn)
+            .unwrap();
+        assert_eq!(i_num, 0);
+        let r_num: i32 = items
+            .filter(itemid.eq(&item.id))
+            .select(rut_count)
+            .get_result(&*conn)
+            .unwrap();
+        assert_eq!(r_num, std::i32::MAX);
+    }
+
+    // fail on the item counter update, after the collect deletion and rut update
+    #[test]
+    #[ignore]
+    fn del_collect_rollback_on_failure() {
+        use crate::schema::collects::dsl::{collects, id as cid};
+        use crate::schema::items::dsl::{id as itemid, items, rut_count};
+        use crate::schema::ruts::dsl::{id as rid, item_count, ruts};
+
+        let mut db = TestDb::new();
+        let uname = uid();
+        let (rut, c) = {
+            let conn = db.conn();
+            let rut = seed_rut(&conn, &uname);
+            let item = seed_item(&conn);
+            let c = diesel::insert_into(collects)
+                .values(&Collect::new(
+                    uid(),
+                    1,
+                    CollectItem {
+                        rut_id: rut.id.clone(),
+                        item_id: item.id.clone(),
+                        item_order: 1,
+                        content: "".to_owned(),
+                        uname: uname.clone(),
+                    },
+                ))
+                .get_result::<Collect>(&*conn)
+                .unwrap();
+            diesel::update(ruts.filter(rid.eq(&rut.id)))
+                .set(item_count.eq(1))
+                .execute(&*conn)
+                .unwrap();
+            // int4 overflow on rut_count - 1
+            diesel::update(items.filter(itemid.eq(&item.id)))
+                .set(rut_count.eq(std::i32::MIN))
+                .execute(&*conn)
+                .unwrap();
+            (rut, c)
+        };
+
+        let res = db.send(DelCollect {
+            collect_id: c.id.clone(),
+            uname: uname.clone(),
+        });
+        assert!(res.is_err());
+
+        let conn = db.conn();
+        let c_num: i64 = collects
+            .filter(cid.eq(&c.id))
+            .count()
+            .get_result(&*conn)
+            .unwrap();
+        assert_eq!(c_num, 1);
+        let i_num: i32 = ruts
+            .filter(rid.eq(&rut.id))
+            .select(item_count)
+            .get_result(&*conn)
+            .unwrap();
+        assert_eq!(i_num, 1);
+    }
+
+    fn item_ids(msg: ItemListMsg) -> Vec<String> {
+        msg.items.into_iter().map(|i| i.id).collect()
+    }
+
+    // item_order asc
+    #[test]
+    #[ignore]
+    fn rut_items_in_collect_order() {
+        use crate::schema::collects::dsl::collects;
+
+        let mut db = TestDb::new();
+        let uname = uid();
+        let (rut, expect) = {
+            let conn = db.conn();
+            let rut = seed_rut(&conn, &uname);
+            let is: Vec<Item> = (0..3).map(|_| seed_item(&conn)).collect();
+            for (i, o) in is.iter().zip(&[2, 3, 1]) {
+                diesel::insert_into(collects)
+                    .values(&Collect::new(
+                        uid(),
+                        *o,
+                        CollectItem {
+                            rut_id: rut.id.clone(),
+                            item_id: i.id.clone(),
+                            item_order: *o,
+                            content: "".to_owned(),
+                            uname: uname.clone(),
+                        },
+                    ))
+                    .execute(&*conn)
+                    .unwrap();
+            }
+            (rut, vec![is[2].id.clone(), is[0].id.clone(), is[1].id.clone()])
+        };
+
+        let msg = db.send(QueryItems::RutID(rut.id)).unwrap();
+        assert_eq!(item_ids(msg), expect);
+    }
+
+    // count desc, paged per cursor
+    #[test]
+    #[ignore]
+    fn tag_items_in_tag_order() {
+        use crate::schema::tagitems::dsl::tagitems;
+
+        let mut db = TestDb::new();
+        let (tag, expect) = {
+            let conn = db.conn();
+            let tag = seed_tag(&conn);
+            let is: Vec<Item> = (0..3).map(|_| seed_item(&conn)).collect();
+            for (i, (n, s)) in is.iter().zip(&[(2, 10), (5, 30), (1, 20)]) {
+                let mut ti = TagItem::new(tag.tname.clone(), i.id.clone());
+                ti.count = *n;
+                ti.tag_at = ago(*s);
+                diesel::insert_into(tagitems).values(&ti).execute(&*conn).unwrap();
+            }
+            (tag, vec![is[1].id.clone(), is[0].id.clone(), is[2].id.clone()])
+        };
+
+        let page = |c: Option<String>| Paging::new(1, c, Some(2));
+        let first = db
+            .send(QueryItems::TagID(tag.tname.clone(), false, page(None)))
+            .unwrap();
+        let cursor = first.next_cursor.clone();
+        assert_eq!(item_ids(first), expect[..2].to_vec());
+        let second = db
+            .send(QueryItems::TagID(tag.tname, false, page(Some(cursor))))
+            .unwrap();
+        assert_eq!(item_ids(second), expect[2..].to_vec());
+    }
+
+    // rut_count desc, paged per cursor
+    #[test]
+    #[ignore]
+    fn key_items_in_rut_count_order() {
+        use crate::schema::items::dsl::{id as itemid, items, rut_count, title};
+
+        let mut db = TestDb::new();
+        let key = uid();
+        let expect = {
+            let conn = db.conn();
+            let is: Vec<Item> = (0..3).map(|_| seed_item(&conn)).collect();
+            for (i, n) in is.iter().zip(&[5, 9, 1]) {
+                diesel::update(items.filter(itemid.eq(&i.id)))
+                    .set((rut_count.eq(*n), title.eq(format!("{} {}", key, n))))
+                    .execute(&*conn)
+                    .unwrap();
+            }
+            vec![is[1].id.clone(), is[0].id.clone(), is[2].id.clone()]
+        };
+
+        let kw = format!("%{}%", key);
+        let page = |c: Option<String>| Paging::new(1, c, Some(2));
+        let first = db
+            .send(QueryItems::KeyID(kw.clone(), "".to_owned(), "".to_owned(), page(None)))
+            .unwrap();
+        let cursor = first.next_cursor.clone();
+        assert_eq!(item_ids(first), expect[..2].to_vec());
+        let second = db
+            .send(QueryItems::KeyID(kw, "".to_owned(), "".to_owned(), page(Some(cursor))))
+            .unwrap();
+        assert_eq!(item_ids(second), expect[2..].to_vec());
+    }
+
+    // star_at desc, per flag
+    #[test]
+    #[ignore]
+    fn user_star_items_in_star_order() {
+        use crate::schema::staritems::dsl::staritems;
+
+        let mut db = TestDb::new();
+        let uname = uid();
+        let expect = {
+            let conn = db.conn();
+            let is: Vec<Item> = (0..4).map(|_| seed_item(&conn)).collect();
+            for (i, (s, f)) in is.iter().zip(&[(30, 1), (10, 1), (20, 1), (5, 3)]) {
+                diesel::insert_into(staritems)
+                    .values(&StarItem {
+                        id: uid(),
+                        uname: uname.clone(),
+                        item_id: i.id.clone(),
+                        star_at: ago(*s),
+                        note: "".to_owned(),
+                        flag: *f,
+                        rate: 0,
+                    })
+                    .execute(&*conn)
+                    .unwrap();
+            }
+            vec![is[1].id.clone(), is[2].id.clone(), is[0].id.clone()]
+        };
+
+        let msg = db
+            .send(QueryItems::UserID(uname, 1, Paging::new(1, None, None)))
+            .unwrap();
+        assert_eq!(item_ids(msg), expect);
+    }
+
+    // a new url resets its status, the cover kept as is
+    #[test]
+    #[ignore]
+    fn update_item_resets_link_check() {
+        use crate::schema::items::dsl::{
+            checked_at, cover_status, dead_link, id as itemid, items, url, url_status,
+        };
+
+        let mut db = TestDb::new();
+        let item = {
+            let conn = db.conn();
+            let item = seed_item(&conn);
+            diesel::update(items.filter(itemid.eq(&item.id)))
+                .set((
+                    url.eq("https://example.com/old-url"),
+                    url_status.eq(404),
+                    cover_status.eq(200),
+                    dead_link.eq(true),
+                    checked_at.eq(Some(ago(60))),
+                ))
+                .get_result::<Item>(&*conn)
+                .unwrap()
+        };
+        let update = |u: &str, c: &str| UpdateItem {
+            id: item.id.clone(),
+            title: item.title.clone(),
+            uiid: item.uiid.clone(),
+            authors: item.authors.clone(),
+            pub_at: item.pub_at.clone(),
+            publisher: item.publisher.clone(),
+            category: item.category.clone(),
+            url: u.to_owned(),
+            cover: c.to_owned(),
+            edition: item.edition.clone(),
+            detail: item.detail.clone(),
+        };
+
+        // not changed, kept
+        let kept = db.send(update(&item.url, &item.cover)).unwrap().item;
+        assert_eq!(kept.url_status, 404);
+        assert!(kept.dead_link);
+        assert!(kept.checked_at.is_some());
+
+        let new_url = "https://example.com/new-url";
+        let up = db.send(update(new_url, &item.cover)).unwrap().item;
+        assert_eq!(up.url_status, 0);
+        assert_eq!(up.cover_status, 200);
+        assert!(!up.dead_link);
+        assert!(up.checked_at.is_none());
+    }
+
+    // both collected, starred and tagged: one of each kept, from redirected to to
+    #[test]
+    #[ignore]
+    fn merge_item_combines_relations() {
+        use crate::schema::collects::dsl::{collects, rut_id};
+        use crate::schema::ruts::dsl::{id as rid, item_count as r_item_count, ruts};
+        use crate::schema::staritems::dsl::{staritems, uname as s_uname};
+        use crate::schema::tagitems::dsl::{tagitems, tname as ti_tname};
+        use crate::schema::tags::dsl::{item_count as t_item_count, tags, tname};
+
+        let mut db = TestDb::new();
+        let uname = uid();
+        let (rut, tag, from, to) = {
+            let conn = db.conn();
+            let rut = seed_rut(&conn, &uname);
+            let tag = seed_tag(&conn);
+            let from = seed_item(&conn);
+            let to = seed_item(&conn);
+            for (i, (o, f)) in [&from, &to].iter().zip(&[(1, 3), (2, 1)]) {
+                diesel::insert_into(collects)
+                    .values(&Collect::new(
+                        uid(),
+                        *o,
+                        CollectItem {
+                            rut_id: rut.id.clone(),
+                            item_id: i.id.clone(),
+                            item_order: *o,
+                            content: "".to_owned(),
+                            uname: uname.clone(),
+                        },
+                    ))
+                    .execute(&*conn)
+                    .unwrap();
+                diesel::insert_into(staritems)
+                    .values(&StarItem {
+                        id: uid(),
+                        uname: uname.clone(),
+                        item_id: i.id.clone(),
+                        star_at: ago(10),
+                        note: "".to_owned(),
+                        flag: *f,
+                        rate: 0,
+                    })
+                    .execute(&*conn)
+                    .unwrap();
+                diesel::insert_into(tagitems)
+                    .values(&TagItem::new(tag.tname.clone(), i.id.clone()))
+                    .execute(&*conn)
+                    .unwrap();
+            }
+            diesel::update(ruts.filter(rid.eq(&rut.id)))
+                .set(r_item_count.eq(2))
+                .execute(&*conn)
+                .unwrap();
+            diesel::update(tags.filter(tname.eq(&tag.tname)))
+                .set(t_item_count.eq(2))
+                .execute(&*conn)
+                .unwrap();
+            (rut, tag, from, to)
+        };
+
+        let merged = db
+            .send(MergeItem {
+                from: from.id.clone(),
+                to: to.id.clone(),
+                uname: uname.clone(),
+            })
+            .unwrap()
+            .item;
+        assert_eq!(merged.id, to.id);
+        assert_eq!(merged.rut_count, 1);
+        assert_eq!(merged.done_count, 1);
+
+        {
+            let conn = db.conn();
+            let cs = collects
+                .filter(rut_id.eq(&rut.id))
+                .load::<Collect>(&*conn)
+                .unwrap();
+            assert_eq!(cs.len(), 1);
+            assert_eq!(cs[0].item_id, to.id);
+            assert_eq!(cs[0].item_order, 1);
+            let i_num: i32 = ruts
+                .filter(rid.eq(&rut.id))
+                .select(r_item_count)
+                .get_result(&*conn)
+                .unwrap();
+            assert_eq!(i_num, 1);
+
+            // the further flag kept
+            let sts = staritems
+                .filter(s_uname.eq(&uname))
+                .load::<StarItem>(&*conn)
+                .unwrap();
+            assert_eq!(sts.len(), 1);
+            assert_eq!(sts[0].item_id, to.id);
+            assert_eq!(sts[0].flag, 3);
+
+            // tagged both, count combined
+            let tis = tagitems
+                .filter(ti_tname.eq(&tag.tname))
+                .load::<TagItem>(&*conn)
+                .unwrap();
+            assert_eq!(tis.len(), 1);
+            assert_eq!(tis[0].item_id, to.id);
+            assert_eq!(tis[0].count, 2);
+            let t_num: i32 = tags
+                .filter(tname.eq(&tag.tname))
+                .select(t_item_count)
+                .get_result(&*conn)
+                .unwrap();
+            assert_eq!(t_num, 1);
+        }
+
+        // the old slug redirects
+        let moved = db
+            .send(QueryItem {
+                item_slug: from.slug.clone(),
+            })
+            .unwrap();
+        assert_eq!(moved.status, 301);
+        assert_eq!(moved.item.id, to.id);
+    }
+
+    // an isbn-10 is the same book as its isbn-13
+    #[test]
+    #[ignore]
+    fn new_item_isbn10_of_existing_isbn13() {
+        use crate::schema::items::dsl::{id as itemid, items, uiid, uiid_type};
+
+        let mut db = TestDb::new();
+        let item = {
+            let conn = db.conn();
+            let item = seed_item(&conn);
+            diesel::update(items.filter(itemid.eq(&item.id)))
+                .set((uiid.eq("9780262033848"), uiid_type.eq("isbn")))
+                .execute(&*conn)
+                .unwrap();
+            item
+        };
+
+        let res = db.send(NewItem {
+            title: "the same book".to_owned(),
+            uiid: "0262033844".to_owned(),
+            ..NewItem::new()
+        });
+        match res {
+            Err(ServiceError::Conflict(_, v)) => {
+                assert_eq!(v["field"], "uiid");
+                assert_eq!(v["id"], item.id.as_str());
+            }
+            _ => panic!("isbn-10 duplicate not rejected"),
+        }
+
+        let conn = db.conn();
+        let n: i64 = items
+            .filter(uiid.eq("0262033844"))
+            .count()
+            .get_result(&*conn)
+            .unwrap();
+        assert_eq!(n, 0);
+    }
+}
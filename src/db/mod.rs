@@ -1,10 +1,150 @@
+pub mod crawl;
 pub mod etc;
 pub mod item;
 pub mod rut;
+pub mod search;
 pub mod tag;
 ///  msg handler mod
 // msg handler,
 // handle the msg from view handler(api mod)
 // comm with db
 // send back he result
+// multi-table writes run in one transaction, rollback all on error
 pub mod user;
+
+// test harness: Dba on a single pooled conn in a test transaction, never committed
+// needs a migrated postgres per DATABASE_URL, run: cargo test -- --ignored
+#[cfg(test)]
+pub mod test_util {
+    use actix::dev::ToEnvelope;
+    use actix::{Handler, Message, SyncArbiter, SyncContext};
+    use actix_rt::{System, SystemRunner};
+    use chrono::{Duration, NaiveDateTime, Utc};
+    use diesel::pg::PgConnection;
+    use diesel::prelude::*;
+    use diesel::r2d2::{self, ConnectionManager, CustomizeConnection, Pool, PooledConnection};
+
+    use crate::model::etc::Etc;
+    use crate::model::item::{Item, NewItem};
+    use crate::model::rut::{CreateRut, Rut};
+    use crate::model::tag::Tag;
+    use crate::{DbAddr, Dba};
+
+    #[derive(Debug)]
+    struct TestTransaction;
+
+    impl CustomizeConnection<PgConnection, r2d2::Error> for TestTransaction {
+        fn on_acquire(&self, conn: &mut PgConnection) -> Result<(), r2d2::Error> {
+            conn.begin_test_transaction().map_err(r2d2::Error::QueryError)
+        }
+    }
+
+    pub struct TestDb {
+        sys: SystemRunner,
+        pool: Pool<ConnectionManager<PgConnection>>,
+        addr: DbAddr,
+    }
+
+    impl TestDb {
+        pub fn new() -> Self {
+            let db_url = dotenv::var("DATABASE_URL").expect("DATABASE_URL must be set");
+            let pool = Pool::builder()
+                .max_size(1)
+                .connection_customizer(Box::new(TestTransaction))
+                .build(ConnectionManager::<PgConnection>::new(db_url))
+                .expect("Failed to create pool.");
+            let sys = System::new("test");
+            let p = pool.clone();
+            let addr = SyncArbiter::start(1, move || Dba(p.clone()));
+
+            TestDb { sys, pool, addr }
+        }
+
+        // the only conn, drop it before send
+        pub fn conn(&self) -> PooledConnection<ConnectionManager<PgConnection>> {
+            self.pool.get().expect("Failed to get conn.")
+        }
+
+        pub fn send<M>(&mut self, msg: M) -> M::Result
+        where
+            M: Message + Send + 'static,
+            M::Result: Send,
+            Dba: Handler<M>,
+            SyncContext<Dba>: ToEnvelope<Dba, M>,
+        {
+            let addr = self.addr.clone();
+            self.sys.block_on(addr.send(msg)).expect("Mailbox")
+        }
+    }
+
+    // unique per test, tests run in parallel transactions
+    pub fn uid() -> String {
+        format!("{}", uuid::Uuid::new_v4())
+    }
+
+    // a time s seconds ago, to seed rows in a known order
+    pub fn ago(s: i64) -> NaiveDateTime {
+        Utc::now().naive_utc() - Duration::seconds(s)
+    }
+
+    pub fn seed_rut(conn: &PgConnection, uname: &str) -> Rut {
+        use crate::schema::ruts::dsl::ruts;
+        let id = uid();
+        let rut = Rut::new(
+            id.clone(),
+            format!("r-{}", id),
+            CreateRut {
+                title: "a test rut".to_owned(),
+                url: "".to_owned(),
+                content: "".to_owned(),
+                author: "".to_owned(),
+                uname: uname.to_owned(),
+                credential: "".to_owned(),
+            },
+        );
+        diesel::insert_into(ruts)
+            .values(&rut)
+            .get_result::<Rut>(conn)
+            .expect("Failed to seed rut.")
+    }
+
+    pub fn seed_item(conn: &PgConnection) -> Item {
+        use crate::schema::items::dsl::items;
+        let id = uid();
+        let new_item = NewItem {
+            title: "a test item".to_owned(),
+            authors: "tester".to_owned(),
+            ..NewItem::new()
+        };
+        diesel::insert_into(items)
+            .values(&Item::new(id.clone(), format!("i-{}", id), new_item))
+            .get_result::<Item>(conn)
+            .expect("Failed to seed item.")
+    }
+
+    pub fn seed_tag(conn: &PgConnection) -> Tag {
+        use crate::schema::tags::dsl::tags;
+        diesel::insert_into(tags)
+            .values(&Tag::new(format!("t-{}", uid())))
+            .get_result::<Tag>(conn)
+            .expect("Failed to seed tag.")
+    }
+
+    pub fn seed_etc(conn: &PgConnection, uname: &str) -> Etc {
+        use crate::schema::etcs::dsl::etcs;
+        diesel::insert_into(etcs)
+            .values(&Etc {
+                id: uid(),
+                content: "a test etc".to_owned(),
+                post_at: Utc::now().naive_utc(),
+                petc_id: "".to_owned(),
+                rut_id: "".to_owned(),
+                item_id: "".to_owned(),
+                tname: "".to_owned(),
+                uname: uname.to_owned(),
+                vote: 1,
+            })
+            .get_result::<Etc>(conn)
+            .expect("Failed to seed etc.")
+    }
+}
@@ -1,18 +1,21 @@
 // rut typed model and msg handler
 
 use actix::Handler;
-use chrono::Utc;
+use chrono::{NaiveDateTime, Utc};
 use diesel::prelude::*;
 use diesel::{self, dsl::any, ExpressionMethods, PgTextExpressionMethods, QueryDsl, RunQueryDsl};
+use serde_json::json;
 use uuid::Uuid;
 
 use crate::errors::ServiceError;
 use crate::model::msg::{Msg, RutListMsg, RutMsg, StarStatusMsg};
 use crate::model::rut::{
-    CreateRut, QueryRut, QueryRuts, Rut, StarOrRut, StarRut, StarRutStatus, UpdateRut,
+    CreateRut, DelRut, QueryRut, QueryRuts, Rut, StarOrRut, StarRut, StarRutStatus, UpdateRut,
 };
-use crate::model::PER_PAGE;
-use crate::util::share::gen_slug;
+use crate::db::tag::{tag_descendants, tagged_page, untag_upkeep};
+use crate::db::user::add_timeline;
+use crate::util::share::{gen_slug, parse_time_cursor, sort_by_ids, time_cursor};
+use std::collections::HashSet;
 use crate::Dba;
 
 // handle msg from api::rut.new_rut
@@ -25,32 +28,34 @@ impl Handler<CreateRut> for Dba {
         // retrieve a connecion from pool
         let conn = &self.0.get()?;
 
-        // check if existing per url
-        let new_url = &new_rut.url;
-        if new_url.trim() != "" {
-            let check_rut = ruts.filter(&url.eq(new_url)).load::<Rut>(conn)?.pop();
-            if let Some(r) = check_rut {
-                return Ok(RutMsg {
-                    status: 422,
-                    message: "Existing".to_string(),
-                    rut: r,
-                });
+        conn.transaction::<_, ServiceError, _>(|| {
+            // check if existing per url
+            let new_url = &new_rut.url;
+            if new_url.trim() != "" {
+                let check_rut = ruts.filter(&url.eq(new_url)).load::<Rut>(conn)?.pop();
+                if let Some(r) = check_rut {
+                    return Err(ServiceError::Conflict(
+                        "Existing".into(),
+                        json!({ "field": "url", "id": r.id, "slug": r.slug }),
+                    ));
+                }
             }
-        }
-
-        // new rut
-        let uuid_v4 = uuid::Uuid::new_v4();
-        let uid = format!("{}", uuid_v4);
-        let r_slug = gen_slug("r", &new_rut.title, &uuid_v4);
-        let newrut = Rut::new(uid, r_slug, new_rut);
-        let rut_new = diesel::insert_into(ruts)
-            .values(&newrut)
-            .get_result::<Rut>(conn)?;
 
-        Ok(RutMsg {
-            status: 201,
-            message: "Created".to_string(),
-            rut: rut_new,
+            // new rut
+            let uuid_v4 = uuid::Uuid::new_v4();
+            let uid = format!("{}", uuid_v4);
+            let r_slug = gen_slug("r", &new_rut.title, &uuid_v4);
+            let newrut = Rut::new(uid, r_slug, new_rut);
+            let rut_new = diesel::insert_into(ruts)
+                .values(&newrut)
+                .get_result::<Rut>(conn)?;
+            add_timeline(conn, &rut_new.uname, "create", "rut", &rut_new.id)?;
+
+            Ok(RutMsg {
+                status: 201,
+                message: "Created".to_string(),
+                rut: rut_new,
+            })
         })
     }
 }
@@ -86,8 +91,10 @@ impl Handler<QueryRuts> for Dba {
         let mut id_list: Vec<String> = Vec::new();
         let mut rut_list: Vec<Rut> = Vec::new();
         let mut rut_num = 0; // total
+        let mut next_cursor = "".to_string();
 
         // build id_list per query type
+        // keyset paging per (sort key, id) if cursor, else offset per page
         match per {
             QueryRuts::Index(_) => {
                 rut_list = ruts
@@ -96,92 +103,91 @@ impl Handler<QueryRuts> for Dba {
                     .limit(20)
                     .load::<Rut>(conn)?;
             }
-            QueryRuts::UserID(u, f, p) => {
+            QueryRuts::UserID(u, f, pg) => {
                 if &f == "create" {
-                    let query = ruts.filter(uname.eq(u));
-                    rut_num = query.clone().count().get_result(conn)?;
-                    rut_list = if p < 1 {
-                        // no limit, hope never use
-                        query.order(create_at.desc()).load::<Rut>(conn)?
-                    } else {
-                        query
-                            .order(create_at.desc())
-                            .limit(PER_PAGE.into())
-                            .offset((PER_PAGE * (p - 1)).into())
-                            .load::<Rut>(conn)?
-                    };
+                    rut_num = ruts.filter(uname.eq(&u)).count().get_result(conn)?;
+                    let mut query = ruts.filter(uname.eq(&u)).into_boxed();
+                    if pg.cursor != "" {
+                        let (c_at, c_id) = parse_time_cursor(&pg.cursor)?;
+                        query = query
+                            .filter(create_at.lt(c_at).or(create_at.eq(c_at).and(id.lt(c_id))));
+                    }
+                    rut_list = query
+                        .order((create_at.desc(), id.desc()))
+                        .limit(pg.limit())
+                        .offset(pg.offset())
+                        .load::<Rut>(conn)?;
+                    if let Some(r) = rut_list.last().filter(|_| pg.is_full(rut_list.len())) {
+                        next_cursor = time_cursor(&r.create_at, &r.id);
+                    }
                 } else {
-                    use crate::schema::starruts::dsl::*;
-                    let query = starruts.filter(uname.eq(u));
-                    rut_num = query.clone().count().get_result(conn)?;
-                    id_list = if p < 1 {
-                        // no limit, hope never use
-                        query
-                            .order(star_at.desc())
-                            .select(rut_id)
-                            .load::<String>(conn)?
-                    } else {
-                        query
-                            .order(star_at.desc())
-                            .limit(PER_PAGE.into())
-                            .offset((PER_PAGE * (p - 1)).into())
-                            .select(rut_id)
-                            .load::<String>(conn)?
+                    use crate::schema::starruts::dsl::{
+                        id as sid, rut_id, star_at, starruts, uname as s_uname,
                     };
+                    rut_num = starruts.filter(s_uname.eq(&u)).count().get_result(conn)?;
+                    let mut query = starruts
+                        .filter(s_uname.eq(&u))
+                        .select((rut_id, star_at, sid))
+                        .into_boxed();
+                    if pg.cursor != "" {
+                        let (c_at, c_id) = parse_time_cursor(&pg.cursor)?;
+                        query = query
+                            .filter(star_at.lt(c_at).or(star_at.eq(c_at).and(sid.lt(c_id))));
+                    }
+                    let stars = query
+                        .order((star_at.desc(), sid.desc()))
+                        .limit(pg.limit())
+                        .offset(pg.offset())
+                        .load::<(String, NaiveDateTime, String)>(conn)?;
+                    if let Some(s) = stars.last().filter(|_| pg.is_full(stars.len())) {
+                        next_cursor = time_cursor(&s.1, &s.2);
+                    }
+                    id_list = stars.into_iter().map(|s| s.0).collect();
                 }
             }
-            QueryRuts::ItemID(i, p) => {
-                use crate::schema::collects::dsl::*;
-                let query = collects.filter(item_id.eq(i));
-                rut_num = query.clone().count().get_result(conn)?;
-                id_list = if p < 1 {
-                    // no limit, hope never use
-                    query
-                        .order(collect_at.desc())
-                        .select(rut_id)
-                        .load::<String>(conn)?
-                } else {
-                    query
-                        .order(collect_at.desc())
-                        .limit(PER_PAGE.into())
-                        .offset((PER_PAGE * (p - 1)).into())
-                        .select(rut_id)
-                        .load::<String>(conn)?
+            QueryRuts::ItemID(i, pg) => {
+                use crate::schema::collects::dsl::{
+                    collect_at, collects, id as cid, item_id, rut_id,
                 };
+                rut_num = collects.filter(item_id.eq(&i)).count().get_result(conn)?;
+                let mut query = collects
+                    .filter(item_id.eq(&i))
+                    .select((rut_id, collect_at, cid))
+                    .into_boxed();
+                if pg.cursor != "" {
+                    let (c_at, c_id) = parse_time_cursor(&pg.cursor)?;
+                    query = query
+                        .filter(collect_at.lt(c_at).or(collect_at.eq(c_at).and(cid.lt(c_id))));
+                }
+                let cs = query
+                    .order((collect_at.desc(), cid.desc()))
+                    .limit(pg.limit())
+                    .offset(pg.offset())
+                    .load::<(String, NaiveDateTime, String)>(conn)?;
+                if let Some(c) = cs.last().filter(|_| pg.is_full(cs.len())) {
+                    next_cursor = time_cursor(&c.1, &c.2);
+                }
+                id_list = cs.into_iter().map(|c| c.0).collect();
             }
-            QueryRuts::TagID(t, p) => {
-                use crate::schema::tagruts::dsl::*;
-                let query = tagruts.filter(tname.eq(t));
-                rut_num = query.clone().count().get_result(conn)?;
-                id_list = if p < 1 {
-                    // no limit, hope never use
-                    query
-                        .order(tag_at.desc())
-                        //.order(count.desc())
-                        .select(rut_id)
-                        .load::<String>(conn)?
-                } else {
-                    query
-                        .order(tag_at.desc())
-                        //.order(count.desc())
-                        .limit(PER_PAGE.into())
-                        .offset((PER_PAGE * (p - 1)).into())
-                        .select(rut_id)
-                        .load::<String>(conn)?
-                };
+            QueryRuts::TagID(t, sub, pg) => {
+                let mut tnames = vec![t.clone()];
+                if sub {
+                    // include descendant tags, a rut once even if tagged with several
+                    tnames.extend(tag_descendants(conn, &t)?.into_iter().map(|tg| tg.tname));
+                }
+                let (num, ids, cursor) = tagged_page(conn, "rut", &tnames, &pg)?;
+                rut_num = num;
+                id_list = ids;
+                next_cursor = cursor;
             }
-            QueryRuts::KeyID(k, f, i, p) => {
+            QueryRuts::KeyID(k, f, i, pg) => {
                 // per keyword from taged, created, collected
+                let mut query = ruts.filter(title.ilike(k)).into_boxed();
                 let fr = f.trim();
                 match fr {
                     "user" => {
                         // just use this arm
-                        rut_list = ruts
-                            .filter(&uname.eq(&i))
-                            .filter(&title.ilike(&k))
-                            .order(create_at.desc())
-                            .limit(PER_PAGE.into())
-                            .load::<Rut>(conn)?;
+                        query = query.filter(uname.eq(i));
                     }
                     "tag" => {
                         // hope never use, to optimaze
@@ -190,12 +196,7 @@ impl Handler<QueryRuts> for Dba {
                             .filter(&tname.eq(&i))
                             .select(rut_id)
                             .load::<String>(conn)?;
-                        rut_list = ruts
-                            .filter(&title.ilike(&k))
-                            .filter(&id.eq(any(&ids)))
-                            .order(create_at.desc())
-                            .limit(PER_PAGE.into())
-                            .load::<Rut>(conn)?;
+                        query = query.filter(id.eq(any(ids)));
                     }
                     "item" => {
                         // hope never use, to optimaze
@@ -204,35 +205,40 @@ impl Handler<QueryRuts> for Dba {
                             .filter(&item_id.eq(&i))
                             .select(rut_id)
                             .load::<String>(conn)?;
-                        rut_list = ruts
-                            .filter(&title.ilike(&k))
-                            .filter(&id.eq(any(&ids)))
-                            .order(create_at.desc())
-                            .limit(PER_PAGE.into())
-                            .load::<Rut>(conn)?;
+                        query = query.filter(id.eq(any(ids)));
                     }
                     _ => {
                         // just query per keyword, hope never use
-                        rut_list = ruts
-                            .filter(&title.ilike(&k))
-                            .order(create_at.desc())
-                            .limit(PER_PAGE.into())
-                            .load::<Rut>(conn)?;
                     }
                 }
+                if pg.cursor != "" {
+                    let (c_at, c_id) = parse_time_cursor(&pg.cursor)?;
+                    query = query
+                        .filter(create_at.lt(c_at).or(create_at.eq(c_at).and(id.lt(c_id))));
+                }
+                rut_list = query
+                    .order((create_at.desc(), id.desc()))
+                    .limit(pg.limit())
+                    .offset(pg.offset())
+                    .load::<Rut>(conn)?;
+                if let Some(r) = rut_list.last().filter(|_| pg.is_full(rut_list.len())) {
+                    next_cursor = time_cursor(&r.create_at, &r.id);
+                }
             }
         }
         // build rut_list
         if id_list.len() > 0 {
             let mut rut_query = ruts.filter(&id.eq(any(&id_list))).load::<Rut>(conn)?;
+            sort_by_ids(&mut rut_query, &id_list, |r| &r.id);
             rut_list.append(&mut rut_query);
         }
 
         Ok(RutListMsg {
-            status: 201,
+            status: 200,
             message: "Success".to_string(),
             ruts: rut_list,
             count: rut_num as usize,
+            next_cursor,
         })
     }
 }
@@ -264,21 +270,21 @@ impl Handler<UpdateRut> for Dba {
                     .get_result::<User>(conn)?
                     .can(EIDT_PERMIT);
 
-        let rut_update = if check_permission {
-            diesel::update(&old_rut)
-                .set((
-                    title.eq(rut.title),
-                    url.eq(rut.url),
-                    content.eq(rut.content),
-                    author.eq(rut.author),
-                    credential.eq(rut.credential),
-                    renew_at.eq(Utc::now().naive_utc()),
-                    slug.eq(r_slug),
-                ))
-                .get_result::<Rut>(conn)?
-        } else {
-            old_rut
-        };
+        if !check_permission {
+            return Err(ServiceError::Forbidden);
+        }
+
+        let rut_update = diesel::update(&old_rut)
+            .set((
+                title.eq(rut.title),
+                url.eq(rut.url),
+                content.eq(rut.content),
+                author.eq(rut.author),
+                credential.eq(rut.credential),
+                renew_at.eq(Utc::now().naive_utc()),
+                slug.eq(r_slug),
+            ))
+            .get_result::<Rut>(conn)?;
 
         Ok(RutMsg {
             status: 201,
@@ -296,66 +302,65 @@ impl Handler<StarOrRut> for Dba {
         use crate::schema::starruts::dsl::*;
         let conn = &self.0.get()?;
 
-        use crate::schema::ruts::dsl::{
-            comment_count, id as rid, item_count, ruts, star_count, vote,
-        };
-        let rut_query = ruts
-            .filter(&rid.eq(&rstar.rut_id))
-            .get_result::<Rut>(conn)?;
-        let s_count = rut_query.star_count;
-
-        match rstar.action {
-            1 => {
-                let uid = format!("{}", uuid::Uuid::new_v4());
-                let new_star = StarRut {
-                    id: uid,
-                    uname: rstar.clone().uname,
-                    rut_id: rstar.clone().rut_id,
-                    star_at: Utc::now().naive_utc(),
-                    note: rstar.clone().note,
-                };
-                diesel::insert_into(starruts)
-                    .values(&new_star)
-                    .execute(conn)?;
-                // to update star_count + 1 in rut
-                diesel::update(ruts.filter(&rid.eq(&rstar.rut_id)))
-                    .set((
-                        star_count.eq(star_count + 1),
-                        // cal vote, to be task
-                        vote.eq(item_count * 2 + comment_count + star_count),
-                    ))
-                    .execute(conn)?;
-
-                Ok(StarStatusMsg {
-                    status: 200,
-                    message: "star".to_string(),
-                    count: s_count + 1,
-                })
-            }
-            0 => {
-                diesel::delete(
-                    starruts
-                        .filter(&rut_id.eq(&rstar.rut_id))
-                        .filter(&uname.eq(&rstar.uname)),
-                )
-                .execute(conn)?;
-                // to update the star_count - 1 in rut
-                diesel::update(ruts.filter(&rid.eq(&rstar.rut_id)))
-                    .set(star_count.eq(star_count - 1))
+        conn.transaction::<_, ServiceError, _>(|| {
+            use crate::schema::ruts::dsl::{
+                comment_count, id as rid, item_count, ruts, star_count, vote,
+            };
+            let rut_query = ruts
+                .filter(&rid.eq(&rstar.rut_id))
+                .get_result::<Rut>(conn)?;
+            let s_count = rut_query.star_count;
+
+            match rstar.action {
+                1 => {
+                    let uid = format!("{}", uuid::Uuid::new_v4());
+                    let new_star = StarRut {
+                        id: uid,
+                        uname: rstar.clone().uname,
+                        rut_id: rstar.clone().rut_id,
+                        star_at: Utc::now().naive_utc(),
+                        note: rstar.clone().note,
+                    };
+                    diesel::insert_into(starruts)
+                        .values(&new_star)
+                        .execute(conn)?;
+                    // to update star_count + 1 in rut
+                    diesel::update(ruts.filter(&rid.eq(&rstar.rut_id)))
+                        .set((
+                            star_count.eq(star_count + 1),
+                            // cal vote, to be task
+                            vote.eq(item_count * 2 + comment_count + star_count),
+                        ))
+                        .execute(conn)?;
+                    add_timeline(conn, &rstar.uname, "star", "rut", &rstar.rut_id)?;
+
+                    Ok(StarStatusMsg {
+                        status: 200,
+                        message: "star".to_string(),
+                        count: s_count + 1,
+                    })
+                }
+                0 => {
+                    diesel::delete(
+                        starruts
+                            .filter(&rut_id.eq(&rstar.rut_id))
+                            .filter(&uname.eq(&rstar.uname)),
+                    )
                     .execute(conn)?;
-
-                Ok(StarStatusMsg {
-                    status: 200,
-                    message: "unstar".to_string(),
-                    count: s_count - 1,
-                })
+                    // to update the star_count - 1 in rut
+                    diesel::update(ruts.filter(&rid.eq(&rstar.rut_id)))
+                        .set(star_count.eq(star_count - 1))
+                        .execute(conn)?;
+
+                    Ok(StarStatusMsg {
+                        status: 200,
+                        message: "unstar".to_string(),
+                        count: s_count - 1,
+                    })
+                }
+                _ => Err(ServiceError::BadRequest("400: Invalid Action".into())),
             }
-            _ => Ok(StarStatusMsg {
-                status: 400,
-                message: "unstar".to_string(),
-                count: s_count,
-            }),
-        }
+        })
     }
 }
 
@@ -390,3 +395,450 @@ impl Handler<StarRutStatus> for Dba {
         })
     }
 }
+
+// handle msg from api::rut.delete
+impl Handler<DelRut> for Dba {
+    type Result = Result<Msg, ServiceError>;
+
+    fn handle(&mut self, dr: DelRut, _: &mut Self::Context) -> Self::Result {
+        use crate::schema::ruts::dsl::*;
+        let conn = &self.0.get()?;
+
+        conn.transaction::<_, ServiceError, _>(|| {
+            let rut_q = ruts
+                .filter(&slug.eq(&dr.rut_slug))
+                .get_result::<Rut>(conn)?;
+
+            // check permission
+            use crate::model::user::{User, EIDT_PERMIT};
+            use crate::schema::users::dsl::{users, uname as u_name};
+
+            let check_permission: bool = rut_q.uname == dr.uname
+                || users.filter(&u_name.eq(&dr.uname))
+                        .get_result::<User>(conn)?
+                        .can(EIDT_PERMIT);
+            if !check_permission {
+                return Err(ServiceError::Forbidden);
+            }
+
+            let rutID = rut_q.clone().id;
+
+            // to update the rut_count - 1 in items collected, then del collects
+            use crate::schema::collects::dsl::{collects, item_id, rut_id as c_rut_id};
+            let item_ids = collects
+                .filter(&c_rut_id.eq(&rutID))
+                .select(item_id)
+                .load::<String>(conn)?;
+            if item_ids.len() > 0 {
+                use crate::schema::items::dsl::{id as itemid, items, rut_count};
+                diesel::update(items.filter(&itemid.eq(any(&item_ids))))
+                    .set(rut_count.eq(rut_count - 1))
+                    .execute(conn)?;
+            }
+            diesel::delete(collects.filter(&c_rut_id.eq(&rutID))).execute(conn)?;
+
+            // to update the rut_count - 1 in tags, then del tagruts
+            use crate::schema::tagruts::dsl::{rut_id as t_rut_id, tagruts, tname};
+            let tag_names = tagruts
+                .filter(&t_rut_id.eq(&rutID))
+                .select(tname)
+                .load::<String>(conn)?;
+            for tg in tag_names.iter() {
+                untag_upkeep(conn, tg, "rut")?;
+            }
+            diesel::delete(tagruts.filter(&t_rut_id.eq(&rutID))).execute(conn)?;
+
+            // del starruts
+            use crate::schema::starruts::dsl::{rut_id as s_rut_id, starruts};
+            diesel::delete(starruts.filter(&s_rut_id.eq(&rutID))).execute(conn)?;
+
+            // del etcs on rut and all replies under them, and the tags on those etcs
+            use crate::schema::etcs::dsl::{etcs, id as eid, petc_id, rut_id as e_rut_id};
+            let mut etc_ids = etcs
+                .filter(&e_rut_id.eq(&rutID))
+                .select(eid)
+                .load::<String>(conn)?;
+            let mut seen: HashSet<String> = etc_ids.iter().cloned().collect();
+            let mut level = etc_ids.clone();
+            while level.len() > 0 {
+                let replies = etcs
+                    .filter(&petc_id.eq(any(&level)))
+                    .select(eid)
+                    .load::<String>(conn)?;
+                level = replies.into_iter().filter(|r| seen.insert(r.clone())).collect();
+                etc_ids.extend(level.iter().cloned());
+            }
+            if etc_ids.len() > 0 {
+                use crate::schema::tagetcs::dsl::{etc_id, tagetcs, tname as te_name};
+                let etc_tags = tagetcs
+                    .filter(&etc_id.eq(any(&etc_ids)))
+                    .select(te_name)
+                    .load::<String>(conn)?;
+                for etg in etc_tags {
+                    untag_upkeep(conn, &etg, "etc")?;
+                }
+                diesel::delete(tagetcs.filter(&etc_id.eq(any(&etc_ids)))).execute(conn)?;
+            }
+            diesel::delete(etcs.filter(&eid.eq(any(&etc_ids)))).execute(conn)?;
+
+            // perform deletion
+            diesel::delete(&rut_q).execute(conn)?;
+
+            Ok(Msg {
+                status: 200,
+                message: "Deleted".to_string(),
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_util::{ago, seed_etc, seed_item, seed_rut, seed_tag, uid, TestDb};
+    use crate::model::etc::Etc;
+    use crate::model::Paging;
+    use crate::model::item::{Collect, CollectItem};
+    use crate::model::tag::{TagEtc, TagRut};
+    use crate::schema::ruts::dsl::{id, ruts};
+
+    // fail on the last etc tag upkeep, after collects, tagruts and starruts gone
+    #[test]
+    #[ignore]
+    fn del_rut_rollback_on_failure() {
+        use crate::schema::collects::dsl::{collects, rut_id as c_rut_id};
+        use crate::schema::items::dsl::{id as itemid, items, rut_count as i_rut_count};
+        use crate::schema::tagruts::dsl::{rut_id as t_rut_id, tagruts};
+        use crate::schema::tags::dsl::{etc_count, rut_count as t_rut_count, tags, tname};
+
+        let mut db = TestDb::new();
+        let uname = uid();
+        let (rut, item, tag, etag) = {
+            let conn = db.conn();
+            let rut = seed_rut(&conn, &uname);
+            let item = seed_item(&conn);
+            let tag = seed_tag(&conn);
+            let etag = seed_tag(&conn);
+            diesel::insert_into(collects)
+                .values(&Collect::new(
+                    uid(),
+                    1,
+                    CollectItem {
+                        rut_id: rut.id.clone(),
+                        item_id: item.id.clone(),
+                        item_order: 1,
+                        content: "".to_owned(),
+                        uname: uname.clone(),
+                    },
+                ))
+                .execute(&*conn)
+                .unwrap();
+            diesel::update(items.filter(itemid.eq(&item.id)))
+                .set(i_rut_count.eq(1))
+                .execute(&*conn)
+                .unwrap();
+            diesel::insert_into(tagruts)
+                .values(&TagRut::new(tag.tname.clone(), rut.id.clone()))
+                .execute(&*conn)
+                .unwrap();
+            diesel::update(tags.filter(tname.eq(&tag.tname)))
+                .set(t_rut_count.eq(1))
+                .execute(&*conn)
+                .unwrap();
+            // an etc on rut, tagged, int4 overflow on etc_count - 1
+            let etc_id = uid();
+            diesel::insert_into(crate::schema::etcs::table)
+                .values(&Etc {
+                    id: etc_id.clone(),
+                    content: "a test etc".to_owned(),
+                    post_at: Utc::now().naive_utc(),
+                    petc_id: "".to_owned(),
+                    rut_id: rut.id.clone(),
+                    item_id: "".to_owned(),
+                    tname: "".to_owned(),
+                    uname: uname.clone(),
+                    vote: 1,
+                })
+                .execute(&*conn)
+                .unwrap();
+            diesel::insert_into(crate::schema::tagetcs::table)
+                .values(&TagEtc::new(etag.tname.clone(), etc_id))
+                .execute(&*conn)
+                .unwrap();
+            diesel::update(tags.filter(tname.eq(&etag.tname)))
+                .set(etc_count.eq(std::i32::MIN))
+                .execute(&*conn)
+                .unwrap();
+            (rut, item, tag, etag)
+        };
+
+        let res = db.send(DelRut {
+            rut_slug: rut.slug.clone(),
+            uname: uname.clone(),
+        });
+        assert!(res.is_err());
+
+        let conn = db.conn();
+        let r_num: i64 = ruts
+            .filter(id.eq(&rut.id))
+            .count()
+            .get_result(&*conn)
+            .unwrap();
+        assert_eq!(r_num, 1);
+        let c_num: i64 = collects
+            .filter(c_rut_id.eq(&rut.id))
+            .count()
+            .get_result(&*conn)
+            .unwrap();
+        assert_eq!(c_num, 1);
+        let i_num: i32 = items
+            .filter(itemid.eq(&item.id))
+            .select(i_rut_count)
+            .get_result(&*conn)
+            .unwrap();
+        assert_eq!(i_num, 1);
+        let tr_num: i64 = tagruts
+            .filter(t_rut_id.eq(&rut.id))
+            .count()
+            .get_result(&*conn)
+            .unwrap();
+        assert_eq!(tr_num, 1);
+        let t_num: i32 = tags
+            .filter(tname.eq(&tag.tname))
+            .select(t_rut_count)
+            .get_result(&*conn)
+            .unwrap();
+        assert_eq!(t_num, 1);
+        let e_num: i32 = tags
+            .filter(tname.eq(&etag.tname))
+            .select(etc_count)
+            .get_result(&*conn)
+            .unwrap();
+        assert_eq!(e_num, std::i32::MIN);
+    }
+
+    // fail on the star counter update, after the star insert
+    #[test]
+    #[ignore]
+    fn star_rut_rollback_on_failure() {
+        use crate::schema::ruts::dsl::star_count;
+        use crate::schema::starruts::dsl::{rut_id as s_rut_id, starruts};
+        use crate::schema::timelines::dsl::{timelines, uname as t_uname};
+
+        let mut db = TestDb::new();
+        let uname = uid();
+        let rut = {
+            let conn = db.conn();
+            let rut = seed_rut(&conn, &uid());
+            // int4 overflow on star_count + 1
+            diesel::update(ruts.filter(id.eq(&rut.id)))
+                .set(star_count.eq(std::i32::MAX))
+                .execute(&*conn)
+                .unwrap();
+            rut
+        };
+
+        let res = db.send(StarOrRut {
+            rut_id: rut.id.clone(),
+            uname: uname.clone(),
+            note: "".to_owned(),
+            action: 1,
+        });
+        assert!(res.is_err());
+
+        let conn = db.conn();
+        let s_num: i64 = starruts
+            .filter(s_rut_id.eq(&rut.id))
+            .count()
+            .get_result(&*conn)
+            .unwrap();
+        assert_eq!(s_num, 0);
+        let t_num: i64 = timelines
+            .filter(t_uname.eq(&uname))
+            .count()
+            .get_result(&*conn)
+            .unwrap();
+        assert_eq!(t_num, 0);
+    }
+
+    // the replies under the rut's etcs go with it, at any depth
+    #[test]
+    #[ignore]
+    fn del_rut_drops_reply_subtree() {
+        use crate::schema::etcs::dsl::{etcs, id as eid, petc_id, rut_id as e_rut_id};
+
+        let mut db = TestDb::new();
+        let uname = uid();
+        let (rut, subtree, other) = {
+            let conn = db.conn();
+            let rut = seed_rut(&conn, &uname);
+            let top = seed_etc(&conn, &uname);
+            diesel::update(etcs.filter(eid.eq(&top.id)))
+                .set(e_rut_id.eq(&rut.id))
+                .execute(&*conn)
+                .unwrap();
+            let mut subtree = vec![top.id.clone()];
+            for _ in 0..2 {
+                let reply = seed_etc(&conn, &uname);
+                diesel::update(etcs.filter(eid.eq(&reply.id)))
+                    .set(petc_id.eq(subtree.last().unwrap()))
+                    .execute(&*conn)
+                    .unwrap();
+                subtree.push(reply.id);
+            }
+            let other = seed_etc(&conn, &uname);
+            (rut, subtree, other)
+        };
+
+        let res = db.send(DelRut {
+            rut_slug: rut.slug.clone(),
+            uname: uname.clone(),
+        });
+        assert!(res.is_ok());
+
+        let conn = db.conn();
+        let e_num: i64 = etcs
+            .filter(eid.eq(any(&subtree)))
+            .count()
+            .get_result(&*conn)
+            .unwrap();
+        assert_eq!(e_num, 0);
+        let o_num: i64 = etcs
+            .filter(eid.eq(&other.id))
+            .count()
+            .get_result(&*conn)
+            .unwrap();
+        assert_eq!(o_num, 1);
+    }
+
+    fn rut_ids(msg: RutListMsg) -> Vec<String> {
+        msg.ruts.into_iter().map(|r| r.id).collect()
+    }
+
+    // star_at desc
+    #[test]
+    #[ignore]
+    fn user_star_ruts_in_star_order() {
+        use crate::schema::starruts::dsl::starruts;
+
+        let mut db = TestDb::new();
+        let uname = uid();
+        let expect = {
+            let conn = db.conn();
+            let rs: Vec<Rut> = (0..3).map(|_| seed_rut(&conn, &uid())).collect();
+            for (r, s) in rs.iter().zip(&[20, 30, 10]) {
+                diesel::insert_into(starruts)
+                    .values(&StarRut {
+                        id: uid(),
+                        uname: uname.clone(),
+                        rut_id: r.id.clone(),
+                        star_at: ago(*s),
+                        note: "".to_owned(),
+                    })
+                    .execute(&*conn)
+                    .unwrap();
+            }
+            vec![rs[2].id.clone(), rs[0].id.clone(), rs[1].id.clone()]
+        };
+
+        let msg = db
+            .send(QueryRuts::UserID(uname, "star".to_owned(), Paging::new(1, None, None)))
+            .unwrap();
+        assert_eq!(rut_ids(msg), expect);
+    }
+
+    // collect_at desc
+    #[test]
+    #[ignore]
+    fn item_ruts_in_collect_order() {
+        use crate::schema::collects::dsl::collects;
+
+        let mut db = TestDb::new();
+        let (item, expect) = {
+            let conn = db.conn();
+            let item = seed_item(&conn);
+            let rs: Vec<Rut> = (0..3).map(|_| seed_rut(&conn, &uid())).collect();
+            for (r, s) in rs.iter().zip(&[10, 30, 20]) {
+                let mut c = Collect::new(
+                    uid(),
+                    1,
+                    CollectItem {
+                        rut_id: r.id.clone(),
+                        item_id: item.id.clone(),
+                        item_order: 1,
+                        content: "".to_owned(),
+                        uname: r.uname.clone(),
+                    },
+                );
+                c.collect_at = ago(*s);
+                diesel::insert_into(collects).values(&c).execute(&*conn).unwrap();
+            }
+            (item, vec![rs[0].id.clone(), rs[2].id.clone(), rs[1].id.clone()])
+        };
+
+        let msg = db
+            .send(QueryRuts::ItemID(item.id, Paging::new(1, None, None)))
+            .unwrap();
+        assert_eq!(rut_ids(msg), expect);
+    }
+
+    // count desc
+    #[test]
+    #[ignore]
+    fn tag_ruts_in_tag_order() {
+        use crate::schema::tagruts::dsl::tagruts;
+
+        let mut db = TestDb::new();
+        let (tag, expect) = {
+            let conn = db.conn();
+            let tag = seed_tag(&conn);
+            let rs: Vec<Rut> = (0..3).map(|_| seed_rut(&conn, &uid())).collect();
+            for (r, (n, s)) in rs.iter().zip(&[(1, 10), (3, 30), (2, 20)]) {
+                let mut tr = TagRut::new(tag.tname.clone(), r.id.clone());
+                tr.count = *n;
+                tr.tag_at = ago(*s);
+                diesel::insert_into(tagruts).values(&tr).execute(&*conn).unwrap();
+            }
+            (tag, vec![rs[1].id.clone(), rs[2].id.clone(), rs[0].id.clone()])
+        };
+
+        let msg = db
+            .send(QueryRuts::TagID(tag.tname, false, Paging::new(1, None, None)))
+            .unwrap();
+        assert_eq!(rut_ids(msg), expect);
+    }
+
+    // per tag and descendants, a rut tagged with both listed once
+    #[test]
+    #[ignore]
+    fn sub_tag_ruts_distinct() {
+        use crate::schema::tagruts::dsl::tagruts;
+        use crate::schema::tags::dsl::{pname, tags, tname};
+
+        let mut db = TestDb::new();
+        let (ptag, expect) = {
+            let conn = db.conn();
+            let ptag = seed_tag(&conn);
+            let ctag = seed_tag(&conn);
+            diesel::update(tags.filter(tname.eq(&ctag.tname)))
+                .set(pname.eq(&ptag.tname))
+                .execute(&*conn)
+                .unwrap();
+            let r1 = seed_rut(&conn, &uid());
+            let r2 = seed_rut(&conn, &uid());
+            for (t, r, n) in &[(&ptag, &r1, 2), (&ctag, &r1, 2), (&ctag, &r2, 3)] {
+                let mut tr = TagRut::new(t.tname.clone(), r.id.clone());
+                tr.count = *n;
+                diesel::insert_into(tagruts).values(&tr).execute(&*conn).unwrap();
+            }
+            // r1 per its count summed over both tags
+            (ptag, vec![r1.id.clone(), r2.id.clone()])
+        };
+
+        let msg = db
+            .send(QueryRuts::TagID(ptag.tname, true, Paging::new(1, None, None)))
+            .unwrap();
+        assert_eq!(msg.count, 2);
+        assert_eq!(rut_ids(msg), expect);
+    }
+}
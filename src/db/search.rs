@@ -0,0 +1,89 @@
+// search msg handler
+
+use actix::Handler;
+use diesel::prelude::*;
+use diesel::sql_query;
+use diesel::sql_types::{BigInt, Text};
+
+use crate::errors::ServiceError;
+use crate::model::msg::SearchMsg;
+use crate::model::search::{Search, SearchFacet, SearchHit, SearchRow};
+use crate::model::PER_PAGE;
+use crate::Dba;
+
+// rank and page the matched first, then headline the page only,
+// the facets share the matched CTE, as rows of kind facet
+// $1: keyword, $2: type or "", $3: limit, $4: offset
+const SEARCH_SQL: &str = "
+    WITH kw AS (SELECT plainto_tsquery('english', $1) AS q),
+    matched AS (
+        SELECT 'rut' AS obj, r.id, r.title, r.slug,
+            r.title || ' ' || r.content AS doc, ts_rank(r.tsv, kw.q) AS rank
+        FROM ruts r, kw WHERE r.tsv @@ kw.q
+        UNION ALL
+        SELECT 'item' AS obj, i.id, i.title, i.slug,
+            i.title || ' ' || i.authors || ' ' || i.detail AS doc, ts_rank(i.tsv, kw.q) AS rank
+        FROM items i, kw WHERE i.tsv @@ kw.q
+        UNION ALL
+        SELECT 'tag' AS obj, t.id, t.tname AS title, t.tname AS slug,
+            t.tname || ' ' || t.intro AS doc, ts_rank(t.tsv, kw.q) AS rank
+        FROM tags t, kw WHERE t.tsv @@ kw.q
+        UNION ALL
+        SELECT 'etc' AS obj, e.id, '' AS title, e.id AS slug,
+            e.content AS doc, ts_rank(e.tsv, kw.q) AS rank
+        FROM etcs e, kw WHERE e.tsv @@ kw.q
+    ),
+    page AS (
+        SELECT * FROM matched WHERE ($2 = '' OR obj = $2)
+        ORDER BY rank DESC, id LIMIT $3 OFFSET $4
+    )
+    SELECT 'hit' AS kind, p.obj, p.id, p.title, p.slug,
+        ts_headline('english', p.doc, kw.q, 'StartSel=<mark>, StopSel=</mark>, MaxWords=35, MinWords=15') AS snippet,
+        p.rank, 0::BIGINT AS count
+    FROM page p, kw
+    UNION ALL
+    SELECT 'facet' AS kind, m.obj, '' AS id, '' AS title, '' AS slug, '' AS snippet,
+        0::REAL AS rank, COUNT(*) AS count
+    FROM matched m GROUP BY m.obj
+    ORDER BY kind DESC, rank DESC, count DESC, id
+";
+
+// handle msg from api::search.search
+impl Handler<Search> for Dba {
+    type Result = Result<SearchMsg, ServiceError>;
+
+    fn handle(&mut self, sq: Search, _: &mut Self::Context) -> Self::Result {
+        let conn = &self.0.get()?;
+
+        let p = std::cmp::max(sq.page, 1);
+        let kw = sq.kw.trim().to_string();
+        let ty = sq.ty.trim().to_string();
+
+        let rows = sql_query(SEARCH_SQL)
+            .bind::<Text, _>(&kw)
+            .bind::<Text, _>(&ty)
+            .bind::<BigInt, _>(i64::from(PER_PAGE))
+            .bind::<BigInt, _>(i64::from(PER_PAGE) * (i64::from(p) - 1))
+            .load::<SearchRow>(conn)?;
+
+        // hit count per type, always for all types
+        let (hit_rows, facet_rows): (Vec<SearchRow>, Vec<SearchRow>) =
+            rows.into_iter().partition(|r| r.kind == "hit");
+        let hits: Vec<SearchHit> = hit_rows.into_iter().map(|r| r.into_hit()).collect();
+        let facets: Vec<SearchFacet> = facet_rows.into_iter().map(|r| r.into_facet()).collect();
+
+        let hit_num = facets
+            .iter()
+            .filter(|f| ty == "" || f.obj == ty)
+            .map(|f| f.count as usize)
+            .sum();
+
+        Ok(SearchMsg {
+            status: 200,
+            message: "Success".to_string(),
+            hits,
+            facets,
+            count: hit_num,
+        })
+    }
+}
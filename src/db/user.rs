@@ -2,12 +2,23 @@
 
 use actix::Handler;
 use bcrypt::{hash, verify, DEFAULT_COST};
+use chrono::{Duration, Utc};
 use diesel::prelude::*;
+use diesel::dsl::any;
+use serde_json::json;
 use uuid::Uuid;
 
 use crate::errors::ServiceError;
-use crate::model::msg::{AuthMsg, Msg};
-use crate::model::user::{AuthUser, ChangePsw, CheckUser, QueryUser, RegUser, UpdateUser, User};
+use crate::model::msg::{AuthMsg, FollowListMsg, Msg, PermitMsg, StarStatusMsg, TimelineMsg};
+use crate::model::user::{
+    decode_email_token, encode_email_token, AuthUser, ChangePsw, CheckSession, CheckUser,
+    ConfirmEmail, Follow, FollowOrNot, FollowStatus, PswReset, QueryFollows, QueryTimelines,
+    QueryUser, RefreshSession, RegUser, ReqResetPsw, ResetPsw, RevokeSession, SendConfirmEmail,
+    QueryPermit, Session, SessionToken, Timeline, UpdatePermit, UpdateUser, User, REFRESH_EXP,
+};
+use crate::model::PER_PAGE;
+use crate::util::mail::{mail_sender, site_url, SendMail};
+use crate::util::share::{parse_time_cursor, time_cursor};
 use crate::Dba;
 
 pub fn hash_password(plain: &str) -> Result<String, ServiceError> {
@@ -34,10 +45,10 @@ impl Handler<RegUser> for Dba {
             .load::<User>(conn)?
             .pop();
         match check_user {
-            Some(_) => Ok(Msg {
-                status: 409,
-                message: "Duplicated".to_string(),
-            }),
+            Some(_) => Err(ServiceError::Conflict(
+                "Duplicated".into(),
+                json!({ "field": "uname" }),
+            )),
             None => {
                 // hash password
                 let pswd: String = hash_password(&msg.password)?;
@@ -61,7 +72,7 @@ impl Handler<RegUser> for Dba {
 // login / signin
 // handle msg from api::auth.signin, auth psw
 impl Handler<AuthUser> for Dba {
-    type Result = Result<CheckUser, ServiceError>;
+    type Result = Result<(CheckUser, SessionToken), ServiceError>;
 
     fn handle(&mut self, msg: AuthUser, _: &mut Self::Context) -> Self::Result {
         use crate::schema::users::dsl::*;
@@ -75,7 +86,8 @@ impl Handler<AuthUser> for Dba {
         if let Some(check_user) = query_user {
             match verify(&msg.password, &check_user.password) {
                 Ok(valid) if valid => {
-                    return Ok(check_user.into());
+                    let session = new_session(conn, &check_user.uname)?;
+                    return Ok((check_user.into(), session));
                 }
                 _ => (),
             }
@@ -104,17 +116,29 @@ impl Handler<QueryUser> for Dba {
 // edit user
 // handle msg from api::auth.update_user
 impl Handler<UpdateUser> for Dba {
-    type Result = Result<CheckUser, ServiceError>;
+    type Result = Result<(CheckUser, Option<SendMail>), ServiceError>;
 
     fn handle(&mut self, user: UpdateUser, _: &mut Self::Context) -> Self::Result {
         use crate::schema::users::dsl::*;
         let conn = &self.0.get()?;
 
-        let update_user = diesel::update(users.filter(&uname.eq(&user.uname)))
-            .set(&user)
+        let old_user = users
+            .filter(&uname.eq(&user.uname))
+            .get_result::<User>(conn)?;
+
+        // reset email_confirmed if email changed, then send mail to confirm
+        let email_changed = old_user.email != user.email;
+        let update_user = diesel::update(&old_user)
+            .set((&user, email_confirmed.eq(old_user.email_confirmed && !email_changed)))
             .get_result::<User>(conn)?;
+        // the mail to confirm, sent via Mailer, a mail failure must not fail the update
+        let mail = if email_changed && update_user.email.trim() != "" {
+            Some(confirm_mail(&update_user.uname, &update_user.email)?)
+        } else {
+            None
+        };
 
-        Ok(update_user.into())
+        Ok((update_user.into(), mail))
     }
 }
 
@@ -136,25 +160,658 @@ impl Handler<ChangePsw> for Dba {
                 Ok(valid) if valid => {
                     // hash psw then update
                     let new_password: String = hash_password(&psw.new_psw)?;
-                    diesel::update(&old)
-                        .set(password.eq(new_password))
+                    conn.transaction::<_, ServiceError, _>(|| {
+                        diesel::update(&old)
+                            .set(password.eq(new_password))
+                            .execute(conn)?;
+                        // sign out other sessions, keep the current
+                        use crate::schema::sessions::dsl::{
+                            id as s_id, revoked, sessions, uname as s_uname,
+                        };
+                        diesel::update(
+                            sessions
+                                .filter(&s_uname.eq(&psw.uname))
+                                .filter(&s_id.ne(&psw.sid)),
+                        )
+                        .set(revoked.eq(true))
                         .execute(conn)?;
 
-                    Ok(Msg {
-                        status: 200,
-                        message: "Success".to_string(),
+                        Ok(Msg {
+                            status: 200,
+                            message: "Success".to_string(),
+                        })
                     })
                 }
-                _ => Ok(Msg {
-                    status: 401,
-                    message: "Somehing Wrong".to_string(),
-                }),
+                _ => Err(ServiceError::Unauthorized),
+            }
+        } else {
+            Err(ServiceError::NotFound("No Existing".into()))
+        }
+    }
+}
+
+// handle msg from api::auth.send_confirm_email
+impl Handler<SendConfirmEmail> for Dba {
+    type Result = Result<Msg, ServiceError>;
+
+    fn handle(&mut self, sc: SendConfirmEmail, _: &mut Self::Context) -> Self::Result {
+        use crate::schema::users::dsl::*;
+        let conn = &self.0.get()?;
+
+        let user = users
+            .filter(&uname.eq(&sc.uname))
+            .get_result::<User>(conn)?;
+        if user.email.trim() == "" {
+            return Err(ServiceError::BadRequest("No Email".into()));
+        }
+        if user.email_confirmed {
+            return Err(ServiceError::BadRequest("Confirmed Already".into()));
+        }
+
+        let m = confirm_mail(&user.uname, &user.email)?;
+        mail_sender().send(&m.to, &m.subject, &m.body)?;
+
+        Ok(Msg {
+            status: 200,
+            message: "Sent".to_string(),
+        })
+    }
+}
+
+// handle msg from api::auth.confirm_email
+impl Handler<ConfirmEmail> for Dba {
+    type Result = Result<Msg, ServiceError>;
+
+    fn handle(&mut self, ce: ConfirmEmail, _: &mut Self::Context) -> Self::Result {
+        use crate::schema::users::dsl::*;
+        let conn = &self.0.get()?;
+
+        let claims = decode_email_token(&ce.token)?;
+        // the email must not be changed since token issued
+        let confirmed = diesel::update(
+            users
+                .filter(&uname.eq(&claims.uname))
+                .filter(&email.eq(&claims.email)),
+        )
+        .set(email_confirmed.eq(true))
+        .execute(conn)?;
+        if confirmed == 0 {
+            return Err(ServiceError::BadRequest("Invalid or Expired Token".into()));
+        }
+
+        Ok(Msg {
+            status: 200,
+            message: "Confirmed".to_string(),
+        })
+    }
+}
+
+// handle msg from api::auth.refresh
+impl Handler<RefreshSession> for Dba {
+    type Result = Result<(CheckUser, SessionToken), ServiceError>;
+
+    fn handle(&mut self, rs: RefreshSession, _: &mut Self::Context) -> Self::Result {
+        use crate::schema::sessions::dsl::*;
+        let conn = &self.0.get()?;
+
+        // refresh token: sid.secret
+        let mut parts = rs.refresh.trim().splitn(2, '.');
+        let (sid, secret) = match (parts.next(), parts.next()) {
+            (Some(i), Some(s)) => (i, s),
+            _ => return Err(ServiceError::Unauthorized),
+        };
+
+        let check_session = sessions.filter(&id.eq(sid)).load::<Session>(conn)?.pop();
+        let ss = match check_session {
+            Some(s) if !s.revoked && s.expire_at > Utc::now().naive_utc() => s,
+            _ => return Err(ServiceError::Unauthorized),
+        };
+        match verify(secret, &ss.refresh_hash) {
+            Ok(valid) if valid => (),
+            _ => {
+                // a rotated token reused, may be stolen, revoke the session
+                diesel::update(&ss).set(revoked.eq(true)).execute(conn)?;
+                return Err(ServiceError::Unauthorized);
+            }
+        }
+
+        // rotate, only if not rotated by another request meanwhile
+        let new_secret = format!("{}", Uuid::new_v4().to_simple());
+        let rotated = diesel::update(
+            sessions
+                .filter(&id.eq(&ss.id))
+                .filter(&refresh_hash.eq(&ss.refresh_hash)),
+        )
+        .set((
+            refresh_hash.eq(hash_password(&new_secret)?),
+            expire_at.eq((Utc::now() + Duration::days(REFRESH_EXP)).naive_utc()),
+            renew_at.eq(Utc::now().naive_utc()),
+        ))
+        .execute(conn)?;
+        if rotated == 0 {
+            return Err(ServiceError::Unauthorized);
+        }
+
+        use crate::schema::users::dsl::{uname as u_name, users};
+        let user = users
+            .filter(&u_name.eq(&ss.uname))
+            .get_result::<User>(conn)?;
+        let session = SessionToken {
+            sid: ss.id.clone(),
+            refresh: format!("{}.{}", ss.id, new_secret),
+        };
+
+        Ok((user.into(), session))
+    }
+}
+
+// handle msg from api::auth.signout
+impl Handler<RevokeSession> for Dba {
+    type Result = Result<Msg, ServiceError>;
+
+    fn handle(&mut self, rs: RevokeSession, _: &mut Self::Context) -> Self::Result {
+        use crate::schema::sessions::dsl::*;
+        let conn = &self.0.get()?;
+
+        if rs.all {
+            diesel::update(sessions.filter(&uname.eq(&rs.uname)))
+                .set(revoked.eq(true))
+                .execute(conn)?;
+        } else {
+            diesel::update(
+                sessions
+                    .filter(&id.eq(&rs.sid))
+                    .filter(&uname.eq(&rs.uname)),
+            )
+            .set(revoked.eq(true))
+            .execute(conn)?;
+        }
+
+        Ok(Msg {
+            status: 200,
+            message: "Signed Out".to_string(),
+        })
+    }
+}
+
+// handle msg from CheckUser::from_request
+impl Handler<CheckSession> for Dba {
+    type Result = Result<i16, ServiceError>;
+
+    fn handle(&mut self, cs: CheckSession, _: &mut Self::Context) -> Self::Result {
+        use crate::schema::sessions::dsl::*;
+        let conn = &self.0.get()?;
+
+        let check_session = sessions.filter(&id.eq(&cs.sid)).load::<Session>(conn)?.pop();
+        match check_session {
+            Some(s) if s.uname == cs.uname && !s.revoked && s.expire_at > Utc::now().naive_utc() => {
+                use crate::schema::users::dsl::{permission, uname as u_name, users};
+                let permit = users
+                    .filter(&u_name.eq(&cs.uname))
+                    .select(permission)
+                    .get_result::<i16>(conn)?;
+                Ok(permit)
             }
+            _ => Err(ServiceError::Unauthorized),
+        }
+    }
+}
+
+// handle msg from api::auth.update_permit
+impl Handler<UpdatePermit> for Dba {
+    type Result = Result<PermitMsg, ServiceError>;
+
+    fn handle(&mut self, up: UpdatePermit, _: &mut Self::Context) -> Self::Result {
+        use crate::schema::users::dsl::*;
+        let conn = &self.0.get()?;
+
+        let user = users.filter(&uname.eq(&up.uname)).get_result::<User>(conn)?;
+        let new_permit = if up.action == 1 {
+            user.permission | up.permit
         } else {
+            user.permission & !up.permit
+        };
+        let permit = diesel::update(&user)
+            .set(permission.eq(new_permit))
+            .returning(permission)
+            .get_result::<i16>(conn)?;
+
+        Ok(PermitMsg {
+            status: 201,
+            message: "Updated".to_string(),
+            uname: up.uname,
+            permission: permit,
+        })
+    }
+}
+
+// handle msg from api::auth.get_permit
+impl Handler<QueryPermit> for Dba {
+    type Result = Result<PermitMsg, ServiceError>;
+
+    fn handle(&mut self, qp: QueryPermit, _: &mut Self::Context) -> Self::Result {
+        use crate::schema::users::dsl::*;
+        let conn = &self.0.get()?;
+
+        let permit = users
+            .filter(&uname.eq(&qp.uname))
+            .select(permission)
+            .get_result::<i16>(conn)?;
+
+        Ok(PermitMsg {
+            status: 200,
+            message: "Success".to_string(),
+            uname: qp.uname,
+            permission: permit,
+        })
+    }
+}
+
+// new session with a refresh token
+fn new_session(conn: &PgConnection, who: &str) -> Result<SessionToken, ServiceError> {
+    use crate::schema::sessions::dsl::sessions;
+
+    let sid = format!("{}", Uuid::new_v4());
+    let secret = format!("{}", Uuid::new_v4().to_simple());
+    let new_ss = Session {
+        id: sid.clone(),
+        uname: who.to_owned(),
+        refresh_hash: hash_password(&secret)?,
+        expire_at: (Utc::now() + Duration::days(REFRESH_EXP)).naive_utc(),
+        revoked: false,
+        create_at: Utc::now().naive_utc(),
+        renew_at: Utc::now().naive_utc(),
+    };
+    diesel::insert_into(sessions)
+        .values(&new_ss)
+        .execute(conn)?;
+
+    Ok(SessionToken {
+        refresh: format!("{}.{}", sid, secret),
+        sid,
+    })
+}
+
+// handle msg from api::auth.req_reset_psw
+impl Handler<ReqResetPsw> for Dba {
+    type Result = Result<Msg, ServiceError>;
+
+    fn handle(&mut self, req: ReqResetPsw, _: &mut Self::Context) -> Self::Result {
+        use crate::schema::pswresets::dsl::*;
+        use crate::schema::users::dsl::{uname as u_name, users};
+        let conn = &self.0.get()?;
+
+        let check_user = users
+            .filter(&u_name.eq(&req.uname))
+            .load::<User>(conn)?
+            .pop();
+
+        // only send to confirmed email, same response anyway
+        if let Some(u) = check_user {
+            if u.email_confirmed && u.email.trim() != "" {
+                let rid = format!("{}", uuid::Uuid::new_v4());
+                let secret = format!("{}", uuid::Uuid::new_v4().to_simple());
+                let new_reset = PswReset {
+                    id: rid.clone(),
+                    uname: u.uname.clone(),
+                    token_hash: hash_password(&secret)?,
+                    expire_at: (Utc::now() + Duration::hours(1)).naive_utc(),
+                    used: false,
+                    create_at: Utc::now().naive_utc(),
+                };
+                diesel::insert_into(pswresets)
+                    .values(&new_reset)
+                    .execute(conn)?;
+
+                let body = format!(
+                    "Hi {},\n\nReset your password via the link below, valid in 1 hour:\n{}/resetpsw/{}.{}\n",
+                    u.uname,
+                    site_url(),
+                    rid,
+                    secret
+                );
+                mail_sender().send(&u.email, "Reset your password on RutHub", &body)?;
+            }
+        }
+
+        Ok(Msg {
+            status: 200,
+            message: "Sent If Existing".to_string(),
+        })
+    }
+}
+
+// handle msg from api::auth.reset_psw
+impl Handler<ResetPsw> for Dba {
+    type Result = Result<Msg, ServiceError>;
+
+    fn handle(&mut self, rp: ResetPsw, _: &mut Self::Context) -> Self::Result {
+        use crate::schema::pswresets::dsl::*;
+        use crate::schema::users::dsl::{password, uname as u_name, users};
+        let conn = &self.0.get()?;
+
+        // token: id.secret
+        let mut parts = rp.token.trim().splitn(2, '.');
+        let (rid, secret) = match (parts.next(), parts.next()) {
+            (Some(r), Some(s)) => (r, s),
+            _ => return Err(ServiceError::BadRequest("Invalid or Expired Token".into())),
+        };
+
+        conn.transaction::<_, ServiceError, _>(|| {
+            // claim the token first, a concurrent claim waits on the row then gets none,
+            // a wrong secret rolls the claim back
+            let mut claimed = diesel::update(
+                pswresets
+                    .filter(&id.eq(rid))
+                    .filter(used.eq(false))
+                    .filter(expire_at.gt(Utc::now().naive_utc())),
+            )
+            .set(used.eq(true))
+            .get_results::<PswReset>(conn)?;
+            let reset = match claimed.pop() {
+                Some(r) if claimed.len() == 0 => r,
+                _ => return Err(ServiceError::BadRequest("Invalid or Expired Token".into())),
+            };
+            match verify(secret, &reset.token_hash) {
+                Ok(valid) if valid => (),
+                _ => return Err(ServiceError::BadRequest("Invalid or Expired Token".into())),
+            }
+
+            // hash psw then update
+            let new_password: String = hash_password(&rp.new_psw)?;
+            diesel::update(users.filter(&u_name.eq(&reset.uname)))
+                .set(password.eq(new_password))
+                .execute(conn)?;
+            // void other tokens of the user
+            diesel::update(pswresets.filter(&uname.eq(&reset.uname)))
+                .set(used.eq(true))
+                .execute(conn)?;
+            // sign out everywhere
+            use crate::schema::sessions::dsl::{revoked, sessions, uname as s_uname};
+            diesel::update(sessions.filter(&s_uname.eq(&reset.uname)))
+                .set(revoked.eq(true))
+                .execute(conn)?;
+
             Ok(Msg {
-                status: 404,
-                message: "No Existing".to_string(),
+                status: 200,
+                message: "Success".to_string(),
             })
+        })
+    }
+}
+
+// mail with a signed link to confirm email
+fn confirm_mail(who: &str, to: &str) -> Result<SendMail, ServiceError> {
+    let token = encode_email_token(who, to)?;
+    let body = format!(
+        "Hi {},\n\nPlease confirm your email via the link below, valid in 24 hours:\n{}/confirm/{}\n",
+        who,
+        site_url(),
+        token
+    );
+    Ok(SendMail {
+        to: to.to_owned(),
+        subject: "Confirm your email on RutHub".to_owned(),
+        body,
+    })
+}
+
+// handle msg from api::auth.follow_or_unfollow
+impl Handler<FollowOrNot> for Dba {
+    type Result = Result<StarStatusMsg, ServiceError>;
+
+    fn handle(&mut self, fo: FollowOrNot, _: &mut Self::Context) -> Self::Result {
+        use crate::schema::follows::dsl::*;
+        let conn = &self.0.get()?;
+
+        // check if the user to follow is existing
+        use crate::schema::users::dsl::{uname as u_name, users};
+        users
+            .filter(&u_name.eq(&fo.fname))
+            .get_result::<User>(conn)?;
+
+        let msg = match fo.action {
+            1 => {
+                let uid = format!("{}", uuid::Uuid::new_v4());
+                let new_follow = Follow {
+                    id: uid,
+                    uname: fo.clone().uname,
+                    fname: fo.clone().fname,
+                    fo_at: Utc::now().naive_utc(),
+                    note: fo.clone().note,
+                };
+                conn.transaction::<_, ServiceError, _>(|| {
+                    diesel::insert_into(follows)
+                        .values(&new_follow)
+                        .execute(conn)?;
+                    add_timeline(conn, &fo.uname, "follow", "user", &fo.fname)
+                })?;
+                "follow"
+            }
+            0 => {
+                diesel::delete(
+                    follows
+                        .filter(&uname.eq(&fo.uname))
+                        .filter(&fname.eq(&fo.fname)),
+                )
+                .execute(conn)?;
+                "unfollow"
+            }
+            _ => return Err(ServiceError::BadRequest("400: Invalid Action".into())),
+        };
+
+        let fo_count: i64 = follows
+            .filter(&fname.eq(&fo.fname))
+            .count()
+            .get_result(conn)?;
+
+        Ok(StarStatusMsg {
+            status: 200,
+            message: msg.to_string(),
+            count: fo_count as i32,
+        })
+    }
+}
+
+// handle msg from api::auth.follow_status
+impl Handler<FollowStatus> for Dba {
+    type Result = Result<StarStatusMsg, ServiceError>;
+
+    fn handle(&mut self, status: FollowStatus, _: &mut Self::Context) -> Self::Result {
+        use crate::schema::follows::dsl::*;
+        let conn = &self.0.get()?;
+
+        let fo_count: i64 = follows
+            .filter(&fname.eq(&status.fname))
+            .count()
+            .get_result(conn)?;
+
+        let check_status = follows
+            .filter(&uname.eq(&status.uname))
+            .filter(&fname.eq(&status.fname))
+            .load::<Follow>(conn)?
+            .pop();
+        let msg = match check_status {
+            Some(_) => "follow",
+            None => "unfollow",
+        };
+
+        Ok(StarStatusMsg {
+            status: 200,
+            message: msg.to_string(),
+            count: fo_count as i32,
+        })
+    }
+}
+
+// handle msg from api::auth.get_follow_list
+impl Handler<QueryFollows> for Dba {
+    type Result = Result<FollowListMsg, ServiceError>;
+
+    fn handle(&mut self, per: QueryFollows, _: &mut Self::Context) -> Self::Result {
+        use crate::schema::follows::dsl::*;
+        let conn = &self.0.get()?;
+
+        let (query, count_query, p) = match per {
+            QueryFollows::Follower(u, p) => (
+                follows.filter(fname.eq(u.clone())).into_boxed(),
+                follows.filter(fname.eq(u)).into_boxed(),
+                p,
+            ),
+            QueryFollows::Following(u, p) => (
+                follows.filter(uname.eq(u.clone())).into_boxed(),
+                follows.filter(uname.eq(u)).into_boxed(),
+                p,
+            ),
+        };
+        // eliminate no limit
+        if p < 1 {
+            return Err(ServiceError::BadRequest(
+                "400: No Requested Resource".into(),
+            ));
+        }
+
+        let fo_num: i64 = count_query.count().get_result(conn)?;
+        let fo_list = query
+            .order(fo_at.desc())
+            .limit(PER_PAGE.into())
+            .offset((PER_PAGE * (p - 1)).into())
+            .load::<Follow>(conn)?;
+
+        Ok(FollowListMsg {
+            status: 200,
+            message: "Success".to_string(),
+            follows: fo_list,
+            count: fo_num as usize,
+        })
+    }
+}
+
+// handle msg from api::auth.get_timelines
+impl Handler<QueryTimelines> for Dba {
+    type Result = Result<TimelineMsg, ServiceError>;
+
+    fn handle(&mut self, per: QueryTimelines, _: &mut Self::Context) -> Self::Result {
+        use crate::schema::timelines::dsl::*;
+        let conn = &self.0.get()?;
+
+        let mut query = timelines.into_boxed();
+        let cursor = match per {
+            QueryTimelines::UserID(u, c) => {
+                query = query.filter(uname.eq(u));
+                c
+            }
+            QueryTimelines::Home(u, c) => {
+                use crate::schema::follows::dsl::{fname, follows, uname as f_uname};
+                let fnames = follows
+                    .filter(&f_uname.eq(&u))
+                    .select(fname)
+                    .load::<String>(conn)?;
+                query = query.filter(uname.eq(any(fnames)));
+                c
+            }
+        };
+
+        // keyset paging per (act_at, id), after the cursor
+        if cursor.trim() != "" {
+            let (c_at, c_id) = parse_time_cursor(cursor.trim())?;
+            query = query.filter(act_at.lt(c_at).or(act_at.eq(c_at).and(id.lt(c_id))));
+        }
+
+        let tl_list = query
+            .order((act_at.desc(), id.desc()))
+            .limit(PER_PAGE.into())
+            .load::<Timeline>(conn)?;
+
+        let next_cursor = match tl_list.last() {
+            Some(t) if tl_list.len() == PER_PAGE as usize => time_cursor(&t.act_at, &t.id),
+            _ => "".to_string(),
+        };
+
+        Ok(TimelineMsg {
+            status: 200,
+            message: "Success".to_string(),
+            timelines: tl_list,
+            next_cursor,
+        })
+    }
+}
+
+// record user's activity in timelines
+pub fn add_timeline(
+    conn: &PgConnection,
+    who: &str,
+    act: &str,
+    ob: &str,
+    ob_id: &str,
+) -> Result<(), ServiceError> {
+    use crate::schema::timelines::dsl::timelines;
+
+    let new_tl = Timeline::new(who, act, ob, ob_id);
+    diesel::insert_into(timelines)
+        .values(&new_tl)
+        .execute(conn)?;
+
+    Ok(())
+}
+
+// check if the user has the permission
+pub fn user_can(conn: &PgConnection, who: &str, permit: i16) -> Result<bool, ServiceError> {
+    use crate::schema::users::dsl::{uname, users};
+
+    let user = users.filter(&uname.eq(who)).get_result::<User>(conn)?;
+    Ok(user.can(permit))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_util::{uid, TestDb};
+
+    // a wrong secret does not burn the token, a used one fails
+    #[test]
+    #[ignore]
+    fn reset_psw_token_single_use() {
+        use crate::schema::pswresets::dsl::{id as rid, pswresets, used};
+
+        let mut db = TestDb::new();
+        let reset = {
+            let conn = db.conn();
+            let who = uid();
+            diesel::insert_into(crate::schema::users::table)
+                .values(&User::new(uid(), who.clone(), hash_password("old-psw").unwrap()))
+                .execute(&*conn)
+                .unwrap();
+            let reset = PswReset {
+                id: uid(),
+                uname: who,
+                token_hash: hash_password("the-secret").unwrap(),
+                expire_at: (Utc::now() + Duration::hours(1)).naive_utc(),
+                used: false,
+                create_at: Utc::now().naive_utc(),
+            };
+            diesel::insert_into(pswresets)
+                .values(&reset)
+                .execute(&*conn)
+                .unwrap();
+            reset
+        };
+        let req = |secret: &str| ResetPsw {
+            token: format!("{}.{}", reset.id, secret),
+            new_psw: "new-psw-1234".to_owned(),
+        };
+
+        assert!(db.send(req("wrong")).is_err());
+        {
+            let conn = db.conn();
+            let u: bool = pswresets
+                .filter(rid.eq(&reset.id))
+                .select(used)
+                .get_result(&*conn)
+                .unwrap();
+            assert!(!u);
         }
+        assert!(db.send(req("the-secret")).is_ok());
+        assert!(db.send(req("the-secret")).is_err());
     }
 }
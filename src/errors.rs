@@ -1,13 +1,24 @@
 // error wrapper, todo: wrap more error
 
 use actix::MailboxError;
-use actix_web::{error::ResponseError, HttpResponse};
+use actix_web::dev::{Body, ResponseBody, ResponseHead, Service, ServiceRequest, ServiceResponse};
+use actix_web::http::{
+    header::{HeaderName, HeaderValue},
+    StatusCode,
+};
+use actix_web::{
+    error::ResponseError,
+    web::{JsonConfig, PathConfig, QueryConfig},
+    Error, HttpMessage, HttpResponse,
+};
 use base64::DecodeError;
 use derive_more::Display;
 use diesel::r2d2::PoolError;
 use diesel::result::{DatabaseErrorKind, Error as DieselError};
+use futures::Future;
+use serde_json::{json, Value};
 use std::convert::From;
-use uuid::Error as ParseError;
+use uuid::{Error as ParseError, Uuid};
 //use jsonwebtoken::errors::{Error as JwtError, ErrorKind as JwtErrorKind};
 
 #[derive(Debug, Display)]
@@ -20,27 +31,166 @@ pub enum ServiceError {
     #[display(fmt = "Unauthorized")]
     Unauthorized,
 
+    // 403
+    #[display(fmt = "Forbidden")]
+    Forbidden,
+
     // 404
     #[display(fmt = "Not Found: {}", _0)]
     NotFound(String),
 
+    // 409, (message, details: field, existing...)
+    #[display(fmt = "Conflict: {}", _0)]
+    Conflict(String, Value),
+
     // 500+
     #[display(fmt = "Internal Server Error: {}", _0)]
     InternalServerError(String),
+
+    // 502, fetch a remote page failed
+    #[display(fmt = "Bad Gateway: {}", _0)]
+    Fetch(FetchError),
+}
+
+// why a fetch failed, to tell if worth a retry
+#[derive(Debug, Display, Clone, Copy, PartialEq)]
+pub enum FetchError {
+    #[display(fmt = "Fetch Timeout")]
+    Timeout,
+    #[display(fmt = "Too Many Redirects")]
+    Redirect,
+    #[display(fmt = "Fetch Status {}", _0)]
+    Status(u16), // http status of the remote
+    #[display(fmt = "Fetch Failed")]
+    Network,
+}
+
+impl FetchError {
+    // timeout, network, 5xx or 429
+    pub fn is_transient(&self) -> bool {
+        match *self {
+            FetchError::Timeout | FetchError::Network => true,
+            FetchError::Status(s) => s == 429 || s >= 500,
+            FetchError::Redirect => false,
+        }
+    }
+}
+
+// error response body, the http status is per code
+#[derive(Serialize, Debug)]
+pub struct ErrorMsg {
+    pub code: String, // stable, machine-readable
+    pub message: String,
+    pub details: Value,     // null if none
+    pub request_id: String, // filled by with_request_id, also in header x-request-id and log
+}
+
+impl ServiceError {
+    // (http status, code)
+    pub fn status_code(&self) -> (StatusCode, &'static str) {
+        match *self {
+            ServiceError::BadRequest(_) => (StatusCode::BAD_REQUEST, "bad_request"),
+            ServiceError::Unauthorized => (StatusCode::UNAUTHORIZED, "unauthorized"),
+            ServiceError::Forbidden => (StatusCode::FORBIDDEN, "forbidden"),
+            ServiceError::NotFound(_) => (StatusCode::NOT_FOUND, "not_found"),
+            ServiceError::Conflict(..) => (StatusCode::CONFLICT, "conflict"),
+            ServiceError::InternalServerError(_) => {
+                (StatusCode::INTERNAL_SERVER_ERROR, "internal_error")
+            }
+            ServiceError::Fetch(_) => (StatusCode::BAD_GATEWAY, "fetch_failed"),
+        }
+    }
 }
 
 // impl ResponseError trait allows to convert errors into http responses with appropriate data
 impl ResponseError for ServiceError {
     fn error_response(&self) -> HttpResponse {
-        match *self {
-            ServiceError::InternalServerError(ref message) => {
-                HttpResponse::InternalServerError().json(message)
+        let (status, code) = self.status_code();
+        let (message, details) = match *self {
+            ServiceError::BadRequest(ref message)
+            | ServiceError::NotFound(ref message)
+            | ServiceError::InternalServerError(ref message) => (message.clone(), Value::Null),
+            ServiceError::Unauthorized => ("Unauthorized".to_owned(), Value::Null),
+            ServiceError::Forbidden => ("Forbidden".to_owned(), Value::Null),
+            ServiceError::Conflict(ref message, ref details) => (message.clone(), details.clone()),
+            ServiceError::Fetch(FetchError::Status(s)) => {
+                (FetchError::Status(s).to_string(), json!({ "status": s }))
             }
-            ServiceError::BadRequest(ref message) => HttpResponse::BadRequest().json(message),
-            ServiceError::Unauthorized => HttpResponse::Unauthorized().json("Unauthorized"),
-            ServiceError::NotFound(ref message) => HttpResponse::NotFound().json(message),
+            ServiceError::Fetch(ref f) => (f.to_string(), Value::Null),
+        };
+        HttpResponse::build(status).json(ErrorMsg {
+            code: code.to_owned(),
+            message,
+            details,
+            request_id: String::new(),
+        })
+    }
+}
+
+// per request id, in request extensions
+#[derive(Clone, Debug)]
+pub struct RequestId(pub String);
+
+// middleware: gen a request id per request, set header x-request-id,
+// fill it in the error envelope and log the error with it
+pub fn with_request_id<S>(
+    req: ServiceRequest,
+    srv: &mut S,
+) -> impl Future<Item = ServiceResponse<Body>, Error = Error>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<Body>, Error = Error>,
+{
+    let request_id = format!("{}", Uuid::new_v4());
+    req.extensions_mut().insert(RequestId(request_id.clone()));
+    srv.call(req).map(move |res| {
+        let mut res = res.map_body(|head, body| fill_request_id(head, body, &request_id));
+        if let Ok(v) = HeaderValue::from_str(&request_id) {
+            res.headers_mut().insert(HeaderName::from_static("x-request-id"), v);
         }
+        res
+    })
+}
+
+fn fill_request_id(head: &mut ResponseHead, body: ResponseBody<Body>, rid: &str) -> ResponseBody<Body> {
+    if !head.status.is_client_error() && !head.status.is_server_error() {
+        return body;
+    }
+    let parsed = match body {
+        ResponseBody::Body(Body::Bytes(ref b)) => serde_json::from_slice::<Value>(b).ok(),
+        _ => None,
+    };
+    let mut msg = match parsed {
+        Some(Value::Object(m)) if m.contains_key("code") => m,
+        _ => return body,
+    };
+    let code = msg.get("code").and_then(|v| v.as_str()).unwrap_or("").to_owned();
+    let message = msg.get("message").and_then(|v| v.as_str()).unwrap_or("").to_owned();
+    if head.status.is_server_error() {
+        error!("[{}] {}: {}", rid, code, message);
+    } else {
+        debug!("[{}] {}: {}", rid, code, message);
     }
+    msg.insert("request_id".to_owned(), Value::String(rid.to_owned()));
+    match serde_json::to_vec(&msg) {
+        Ok(v) => ResponseBody::Body(Body::from(v)),
+        Err(_) => body,
+    }
+}
+
+// extractor rejections as envelope 400, register via App::data
+pub fn json_config() -> JsonConfig {
+    JsonConfig::default()
+        .error_handler(|err, _| ServiceError::BadRequest(format!("Invalid JSON: {}", err)).into())
+}
+
+pub fn query_config() -> QueryConfig {
+    QueryConfig::default()
+        .error_handler(|err, _| ServiceError::BadRequest(format!("Invalid Query: {}", err)).into())
+}
+
+pub fn path_config() -> PathConfig {
+    PathConfig::default()
+        .error_handler(|err, _| ServiceError::BadRequest(format!("Invalid Path: {}", err)).into())
 }
 
 impl From<MailboxError> for ServiceError {
@@ -63,8 +213,24 @@ impl From<DieselError> for ServiceError {
         match error {
             DieselError::DatabaseError(kind, info) => {
                 if let DatabaseErrorKind::UniqueViolation = kind {
+                    // details like: Key (uname)=(xyz) already exists.
                     let msg = info.details().unwrap_or_else(|| info.message()).to_string();
-                    return ServiceError::BadRequest(msg);
+                    let field = msg
+                        .find("Key (")
+                        .and_then(|i| {
+                            let rest = &msg[i + 5..];
+                            rest.find(")=").map(|j| rest[..j].to_owned())
+                        })
+                        .unwrap_or_default();
+                    let constraint = info.constraint_name().unwrap_or("").to_owned();
+                    return ServiceError::Conflict(
+                        "Duplicated".into(),
+                        json!({ "field": field, "constraint": constraint }),
+                    );
+                }
+                // no CheckViolation kind in diesel, per message
+                if info.message().contains("violates check constraint") {
+                    return ServiceError::BadRequest(info.message().to_string());
                 }
                 ServiceError::InternalServerError("datebase".into())
             }
@@ -82,6 +248,22 @@ impl From<PoolError> for ServiceError {
     }
 }
 
+// crawle, per the requested url
+impl From<reqwest::Error> for ServiceError {
+    fn from(error: reqwest::Error) -> Self {
+        let fetch = if error.is_timeout() {
+            FetchError::Timeout
+        } else if error.is_redirect() {
+            FetchError::Redirect
+        } else if let Some(s) = error.status() {
+            FetchError::Status(s.as_u16())
+        } else {
+            FetchError::Network
+        };
+        ServiceError::Fetch(fetch)
+    }
+}
+
 // Base64 decode
 impl From<DecodeError> for ServiceError {
     fn from(_error: DecodeError) -> Self {
@@ -14,6 +14,7 @@ extern crate log;
 use actix::prelude::*;
 use actix::{Actor, SyncContext};
 use actix_web::{
+    error::ResponseError,
     middleware::{Logger},
     web::{self, delete, get, post, put, resource, route, scope},
     App, HttpResponse, HttpServer,
@@ -77,6 +78,11 @@ pub fn init_fern_logger() -> Result<(), fern::InitError> {
     Ok(())
 }
 
+// no route, in the error envelope
+fn not_found() -> HttpResponse {
+    errors::ServiceError::NotFound("No Route".into()).error_response()
+}
+
 fn main() -> std::io::Result<()> {
     // init logger
     init_fern_logger().unwrap_or_default();
@@ -84,12 +90,25 @@ fn main() -> std::io::Result<()> {
     let sys = actix_rt::System::new("rut-server-rust");
     // init actor
     let addr: DbAddr = init_dba();
+    let mailer = util::mail::init_mailer();
+    let spider: bot::SpiderAddr = bot::init_spider()
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))?;
+    // crawl job queue runner
+    bot::crawler::Crawler::new(addr.clone(), spider.clone()).start();
+    // periodic link check and metadata refresh
+    bot::linkcheck::LinkChecker::new(addr.clone(), spider.clone()).start();
 
     let bind_host = dotenv::var("BIND_ADDRESS").unwrap_or("127.0.0.1:8083".to_string());
     // config Server, App, AppState, middleware, service
     HttpServer::new(move || {
         App::new()
             .data(addr.clone())
+            .data(mailer.clone())
+            .data(errors::json_config())
+            .data(errors::query_config())
+            .data(errors::path_config())
+            // innermost, to see the plain body
+            .wrap_fn(errors::with_request_id)
             .wrap(Logger::default())
             .wrap(Cors::default())
             // everything under '/api/' route
@@ -99,6 +118,16 @@ fn main() -> std::io::Result<()> {
                     resource("/signin")
                         .route(post().to_async(api::auth::signin))
                 )
+                // to renew access token
+                .service(
+                    resource("/refresh")
+                        .route(post().to_async(api::auth::refresh))
+                )
+                // to sign out, revoke session
+                .service(
+                    resource("/signout/{all:[0|1]}")
+                        .route(post().to_async(api::auth::signout))
+                )
                 // to register
                 .service(
                     resource("/signup")
@@ -111,6 +140,48 @@ fn main() -> std::io::Result<()> {
                         .route(post().to_async(api::auth::update))
                         .route(put().to_async(api::auth::change_psw))
                 )
+                // grant or revoke permission, get permission
+                .service(
+                    resource("/permit")
+                        .route(put().to_async(api::auth::update_permit))
+                )
+                .service(
+                    resource("/permit/{uname}")
+                        .route(get().to_async(api::auth::get_permit))
+                )
+                .service(
+                    resource("/resetpsw")
+                        .route(post().to_async(api::auth::req_reset_psw))
+                        .route(put().to_async(api::auth::reset_psw))
+                )
+                .service(
+                    resource("/confirm")
+                        .route(post().to_async(api::auth::send_confirm_email))
+                )
+                .service(
+                    resource("/confirm/{token}")
+                        .route(get().to_async(api::auth::confirm_email))
+                )
+                .service(
+                    resource("/follow/{uname}/{action:[0|1]}/{note}")
+                        .route(get().to_async(api::auth::follow_or_unfollow))
+                )
+                .service(
+                    resource("/iffollow/{uname}")
+                        .route(get().to_async(api::auth::follow_status))
+                )
+                .service(
+                    resource("/follows/{per}/{uname}") // per: follower|following ?page=p
+                        .route(get().to_async(api::auth::get_follow_list))
+                )
+                .service(
+                    resource("/activities/{uname}") // ?cursor=
+                        .route(get().to_async(api::auth::get_activities))
+                )
+                .service(
+                    resource("/feed") // ?cursor=
+                        .route(get().to_async(api::auth::get_feed))
+                )
                 .service(
                     resource("/ruts")
                         .route(post().to_async(api::rut::new))
@@ -119,8 +190,8 @@ fn main() -> std::io::Result<()> {
                 .service(
                     resource("/ruts/{slug}")
                         .route(get().to_async(api::rut::get))
-                        .route(post().to_async(api::rut::update)) // can be del, per frontend
-                                                                    //.route(delete().to_async(api::rut::delete))
+                        .route(post().to_async(api::rut::update))
+                        .route(delete().to_async(api::rut::delete))
                 )
                 .service(
                     resource("/ruts/{per}/{perid}") // ?page=p&flag=create|star&kw= fr=
@@ -149,6 +220,33 @@ fn main() -> std::io::Result<()> {
                     resource("/items/{per}/{id}") //?page=p&flag=&kw=url_base64&fr= // special per-url
                         .route(get().to_async(api::item::get_list))
                 )
+                // queue a url to crawl, poll the job
+                .service(
+                    resource("/crawljobs")
+                        .route(post().to_async(api::crawl::new_job))
+                )
+                .service(
+                    resource("/crawljobs/{id}")
+                        .route(get().to_async(api::crawl::get_job))
+                )
+                // item merge and duplicates report, by moderator
+                .service(
+                    resource("/itemmerge")
+                        .route(post().to_async(api::item::merge))
+                )
+                .service(
+                    resource("/itemdups")
+                        .route(get().to_async(api::item::get_dups))
+                )
+                // metadata suggests per link check, by moderator
+                .service(
+                    resource("/suggests")
+                        .route(get().to_async(api::item::get_suggests))
+                )
+                .service(
+                    resource("/suggests/{id}/{action:[0|1]}")
+                        .route(put().to_async(api::item::decide_suggest))
+                )
                 .service(
                     resource("/staritem/{itemid}/{flag:[1|2|3]}/{rate}/{note}")
                         .route(get().to_async(api::item::star_item))
@@ -161,6 +259,10 @@ fn main() -> std::io::Result<()> {
                     resource("/collectitem/{rutid}")
                         .route(post().to_async(api::item::collect_item))
                 )
+                .service(
+                    resource("/collectorder/{rutid}")
+                        .route(post().to_async(api::item::reorder_collect))
+                )
                 .service(
                     resource("/collects/{per}/{id}")
                         .route(get().to_async(api::item::get_collect_list))
@@ -181,6 +283,24 @@ fn main() -> std::io::Result<()> {
                     resource("/tags/{per}/{id}")
                         .route(get().to_async(api::tag::get_list))
                 )
+                // tag ancestors or subtree
+                .service(
+                    resource("/tagtree/{per}/{tname}")
+                        .route(get().to_async(api::tag::get_tree))
+                )
+                // tag alias and merge, by moderator
+                .service(
+                    resource("/tagalias")
+                        .route(put().to_async(api::tag::update_alias))
+                )
+                .service(
+                    resource("/tagalias/{tname}")
+                        .route(get().to_async(api::tag::get_aliases))
+                )
+                .service(
+                    resource("/tagmerge")
+                        .route(post().to_async(api::tag::merge))
+                )
                 .service(
                     resource("/tagr/{action:[0|1]}/{rutid}") // can be merged in totag/action
                         .route(post().to_async(api::tag::tag_rut))
@@ -200,13 +320,27 @@ fn main() -> std::io::Result<()> {
                 .service(
                     resource("/etcs")
                         .route(post().to_async(api::etc::new))
+                        .route(put().to_async(api::etc::update))
+                )
+                .service(
+                    resource("/etcs/{etcid}")
+                        .route(delete().to_async(api::etc::delete))
                 )
                 .service(
                     resource("/etcs/{per}/{perid}")
                         .route(get().to_async(api::etc::get_list))
                 )
-                .default_service(route().to(|| HttpResponse::NotFound()))
+                .service(
+                    resource("/etctree/{per}/{perid}") // ?page=p&depth=d
+                        .route(get().to_async(api::etc::get_tree))
+                )
+                .service(
+                    resource("/search") // ?kw=&ty=rut|item|tag|etc&page=
+                        .route(get().to_async(api::search::search))
+                )
+                .default_service(route().to(not_found))
             )
+            .default_service(route().to(not_found))
     })
     .bind(&bind_host)
     .expect("Can not bind to host")
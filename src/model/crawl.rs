@@ -0,0 +1,125 @@
+// crawl job typed model and msg
+
+use actix::Message;
+use chrono::{NaiveDateTime, Utc};
+use serde_json::{json, Value};
+
+use crate::errors::ServiceError;
+use crate::model::item::NewItem;
+use crate::model::msg::CrawlJobMsg;
+use crate::model::{re_test_url, test_len_limit, Validate, URL_LEN};
+use crate::schema::crawl_jobs;
+
+// some const for crawl job queue
+pub const CRAWL_CONCURRENCY: usize = 4; // jobs running at once
+pub const CRAWL_POLL: u64 = 2; // seconds, to poll due jobs
+pub const POLITE_DELAY: u64 = 10; // seconds, between jobs on a domain
+pub const CRAWL_MAX_ATTEMPTS: i16 = 5;
+pub const RETRY_BASE: i64 = 30; // seconds, backoff: base * 2^(attempts-1)
+pub const CRAWL_USER_MAX: i64 = 20; // jobs pending or running per user
+
+#[derive(
+    Clone, Debug, Serialize, Deserialize, PartialEq, Identifiable, Queryable, QueryableByName,
+    Insertable,
+)]
+#[table_name = "crawl_jobs"]
+pub struct CrawlJob {
+    pub id: String,
+    pub url: String,
+    pub domain: String,
+    pub uname: String,
+    pub status: String, // pending|running|done|failed
+    pub attempts: i16,
+    pub draft: Value, // NewItem once done, {} if not
+    pub error: String,
+    pub next_at: NaiveDateTime,
+    pub create_at: NaiveDateTime,
+    pub update_at: NaiveDateTime,
+}
+
+// CrawlJob's constructor
+impl CrawlJob {
+    pub fn new(uid: String, domain: String, job: NewCrawlJob) -> Self {
+        let now = Utc::now().naive_utc();
+        CrawlJob {
+            id: uid,
+            url: job.url,
+            domain,
+            uname: job.uname,
+            status: "pending".to_owned(),
+            attempts: 0,
+            draft: json!({}),
+            error: "".to_owned(),
+            next_at: now,
+            create_at: now,
+            update_at: now,
+        }
+    }
+}
+
+// as msg to submit a url to crawl
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct NewCrawlJob {
+    pub url: String,
+    #[serde(default)]
+    pub uname: String,
+}
+
+impl Message for NewCrawlJob {
+    type Result = Result<CrawlJobMsg, ServiceError>;
+}
+
+impl Validate for NewCrawlJob {
+    fn validate(&self) -> Result<(), ServiceError> {
+        let check = test_len_limit(&self.url, 8, URL_LEN) && re_test_url(&self.url);
+
+        if check {
+            Ok(())
+        } else {
+            Err(ServiceError::BadRequest("Invalid URL".into()))
+        }
+    }
+}
+
+// as msg to poll a job
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct QueryCrawlJob {
+    pub id: String,
+    pub uname: String, // to check permission
+}
+
+impl Message for QueryCrawlJob {
+    type Result = Result<CrawlJobMsg, ServiceError>;
+}
+
+// as msg from Crawler, to claim due pending jobs, one per domain
+#[derive(Debug, Clone)]
+pub struct ClaimCrawlJobs {
+    pub limit: usize,
+    pub skip: Vec<String>, // domains running or in delay
+}
+
+impl Message for ClaimCrawlJobs {
+    type Result = Result<Vec<CrawlJob>, ServiceError>;
+}
+
+// as msg from Crawler, to save the result of a job
+#[derive(Debug, Clone)]
+pub struct FinishCrawlJob {
+    pub id: String,
+    pub draft: Option<NewItem>, // None if failed
+    pub error: String,
+    pub retry: bool, // if failed per a transient error
+}
+
+impl Message for FinishCrawlJob {
+    type Result = Result<(), ServiceError>;
+}
+
+// as msg from Crawler on start, to re-queue the jobs left running
+#[derive(Debug, Clone)]
+pub struct ResetCrawlJobs;
+
+impl Message for ResetCrawlJobs {
+    type Result = Result<usize, ServiceError>;
+}
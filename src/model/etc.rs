@@ -1,16 +1,19 @@
 // etc typed model and msg handler
 
 use actix::Message;
-use actix_web::{error, Error};
 use chrono::NaiveDateTime;
+use diesel::sql_types::{BigInt, Text};
 
 use crate::errors::ServiceError;
-use crate::model::msg::{EtcListMsg, EtcMsg, Msg};
-use crate::model::{re_test_url, test_len_limit, Validate, TAG_LEN};
+use crate::model::msg::{EtcListMsg, EtcMsg, EtcTreeMsg, Msg};
+use crate::model::{re_test_url, test_len_limit, Paging, Validate, TAG_LEN};
 use crate::schema::etcs;
 
 // use to build select query
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Identifiable, Queryable, Insertable)]
+#[derive(
+    Clone, Debug, Serialize, Deserialize, PartialEq, Identifiable, Queryable, QueryableByName,
+    Insertable,
+)]
 #[table_name = "etcs"]
 pub struct Etc {
     pub id: String,
@@ -38,14 +41,14 @@ impl Message for PostEtc {
 }
 
 impl Validate for PostEtc {
-    fn validate(&self) -> Result<(), Error> {
+    fn validate(&self) -> Result<(), ServiceError> {
         let check_len = test_len_limit(&self.content, 1, 512);
         let check = check_len;
 
         if check {
             Ok(())
         } else {
-            Err(error::ErrorBadRequest("Invalid Input(1-512)"))
+            Err(ServiceError::BadRequest("Invalid Input(1-512)".into()))
         }
     }
 }
@@ -56,21 +59,75 @@ pub struct QueryEtcs {
     // diff way from enum to get
     pub per: String,
     pub perid: String,
-    pub page: i32,
+    pub paging: Paging,
 }
 
 impl Message for QueryEtcs {
     type Result = Result<EtcListMsg, ServiceError>;
 }
 
-// todo
+// etc with its replies, as node of reply tree
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct EtcNode {
+    pub etc: Etc,
+    pub reply_count: i64,    // all replies, not only loaded
+    pub more: i64,           // replies not loaded
+    pub next_cursor: String, // to get the rest, "" if none
+    pub replies: Vec<EtcNode>,
+}
+
+// replies per parent, in reply tree
+#[derive(Clone, Debug, QueryableByName)]
+pub struct ReplyCountRow {
+    #[sql_type = "Text"]
+    pub petc_id: String,
+    #[sql_type = "BigInt"]
+    pub count: i64,
+}
+
+// as msg to get the nested reply tree of rut|item|tag|petc
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct QueryEtcTree {
+    pub per: String,
+    pub perid: String,
+    pub page: i32,  // paging on top level
+    pub depth: i32, // levels of replies to load
+}
+
+impl Message for QueryEtcTree {
+    type Result = Result<EtcTreeMsg, ServiceError>;
+}
+
+// as msg to update etc
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct UpdateEtc {
+    pub id: String,
+    pub content: String,
+    pub uname: String, // to check permission
+}
+
+impl Message for UpdateEtc {
+    type Result = Result<EtcMsg, ServiceError>;
+}
+
+impl Validate for UpdateEtc {
+    fn validate(&self) -> Result<(), ServiceError> {
+        let check_len = test_len_limit(&self.content, 1, 512);
+        let check = check_len;
+
+        if check {
+            Ok(())
+        } else {
+            Err(ServiceError::BadRequest("Invalid Input(1-512)".into()))
+        }
+    }
+}
+
 // as msg to del etc
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct DelEtc {
     pub etc_id: String,
-    pub rut_id: String,  // to update rut after del
-    pub item_id: String, // to update item after del
-    pub uname: String,   // to check permission
+    pub uname: String, // to check permission
 }
 
 impl Message for DelEtc {
@@ -1,13 +1,18 @@
 // item typed model and msg handler
 
 use actix::Message;
-use actix_web::{error, Error};
 use chrono::{NaiveDateTime, Utc};
+use diesel::sql_types::{Array, BigInt, Text};
 
 use crate::errors::ServiceError;
-use crate::model::msg::{CollectMsg, CollectsMsg, ItemListMsg, ItemMsg, Msg, StarItemMsg};
-use crate::model::{re_test_url, test_len_limit, Validate, TITLE_LEN, UIID_LEN};
-use crate::schema::{collects, items, staritems};
+use crate::model::msg::{
+    CollectMsg, CollectsMsg, DupItemsMsg, ItemListMsg, ItemMsg, Msg, StarItemMsg,
+    SuggestListMsg,
+};
+use crate::model::{
+    re_test_url, test_len_limit, uiid_kind, Paging, Validate, TITLE_LEN, UIID_LEN,
+};
+use crate::schema::{collects, itemredirects, items, itemsuggests, staritems};
 use crate::util::share::gen_slug;
 
 // use to build select query
@@ -30,11 +35,17 @@ pub struct Item {
     pub done_count: i32, // num of who done
     pub vote: i32,       //  cal per rut, done, etc
     pub slug: String,    // to do
+    pub url_status: i16, // per link check, 0 unchecked, -1 unreachable
+    pub cover_status: i16,
+    pub dead_link: bool, // url or cover
+    pub checked_at: Option<NaiveDateTime>,
+    pub uiid_type: String, // isbn|doi|arxiv|asin, "" if unknown
 }
 
 // Item's constructor
 impl Item {
     pub fn new(uid: String, slug: String, item: NewItem) -> Self {
+        let uiid_type = uiid_kind(&item.uiid).to_owned();
         Item {
             id: uid,
             title: item.title,
@@ -52,6 +63,11 @@ impl Item {
             done_count: 0,
             vote: 0,
             slug,
+            url_status: 0,
+            cover_status: 0,
+            dead_link: false,
+            checked_at: None,
+            uiid_type,
         }
     }
 }
@@ -94,7 +110,7 @@ impl Message for NewItem {
 }
 
 impl Validate for NewItem {
-    fn validate(&self) -> Result<(), Error> {
+    fn validate(&self) -> Result<(), ServiceError> {
         let url = &self.url.trim();
         let cover = &self.cover.trim();
         let url_test = if url.len() == 0 {
@@ -108,7 +124,7 @@ impl Validate for NewItem {
             re_test_url(cover)
         };
         let check_len = test_len_limit(&self.title, 3, TITLE_LEN)
-            && test_len_limit(&self.uiid, 0, 32)
+            && test_len_limit(&self.uiid, 0, UIID_LEN)
             && test_len_limit(&self.authors, 1, 128)
             && test_len_limit(&self.pub_at, 0, 32)
             && test_len_limit(&self.publisher, 0, 64)
@@ -119,7 +135,7 @@ impl Validate for NewItem {
         if check {
             Ok(())
         } else {
-            Err(error::ErrorBadRequest("Invalid Input"))
+            Err(ServiceError::BadRequest("Invalid Input".into()))
         }
     }
 }
@@ -146,7 +162,7 @@ impl Message for UpdateItem {
 }
 
 impl Validate for UpdateItem {
-    fn validate(&self) -> Result<(), Error> {
+    fn validate(&self) -> Result<(), ServiceError> {
         let url = &self.url.trim();
         let cover = &self.cover.trim();
         let url_test = if url.len() == 0 {
@@ -161,7 +177,7 @@ impl Validate for UpdateItem {
         };
         let check_len = test_len_limit(&self.id, 8, 512)
             && test_len_limit(&self.title, 3, TITLE_LEN)
-            && test_len_limit(&self.uiid, 0, 32)
+            && test_len_limit(&self.uiid, 0, UIID_LEN)
             && test_len_limit(&self.authors, 1, 128)
             && test_len_limit(&self.pub_at, 0, 32)
             && test_len_limit(&self.publisher, 0, 64)
@@ -172,7 +188,7 @@ impl Validate for UpdateItem {
         if check {
             Ok(())
         } else {
-            Err(error::ErrorBadRequest("Invalid Input"))
+            Err(ServiceError::BadRequest("Invalid Input".into()))
         }
     }
 }
@@ -196,9 +212,9 @@ pub enum QueryItems {
     Title(String),
     ItemUrl(String),
     RutID(String),
-    TagID(String),
-    UserID(String, i16, i32),           // (uname, flag, paging)
-    KeyID(String, String, String, i32), // keyword, per, perid(uname|tname), paging
+    TagID(String, bool, Paging), // tname, include descendant tags, paging
+    UserID(String, i16, Paging),           // (uname, flag, paging)
+    KeyID(String, String, String, Paging), // keyword, per, perid(uname|tname), paging
 }
 
 impl Message for QueryItems {
@@ -206,7 +222,7 @@ impl Message for QueryItems {
 }
 
 impl Validate for QueryItems {
-    fn validate(&self) -> Result<(), Error> {
+    fn validate(&self) -> Result<(), ServiceError> {
         let check: bool = match self {
             QueryItems::ItemUrl(url) => re_test_url(url),
             // could do more
@@ -216,7 +232,7 @@ impl Validate for QueryItems {
         if check {
             Ok(())
         } else {
-            Err(error::ErrorBadRequest("Invalid Input"))
+            Err(ServiceError::BadRequest("Invalid Input".into()))
         }
     }
 }
@@ -289,6 +305,31 @@ impl Message for DelCollect {
     type Result = Result<Msg, ServiceError>;
 }
 
+// as msg to move an item to new order in rut
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ReorderCollect {
+    pub rut_id: String,
+    pub collect_id: String,
+    pub item_order: i16, // new order, 1-based
+    pub uname: String,   // to check permission
+}
+
+impl Message for ReorderCollect {
+    type Result = Result<CollectsMsg, ServiceError>;
+}
+
+impl Validate for ReorderCollect {
+    fn validate(&self) -> Result<(), ServiceError> {
+        let check = self.item_order >= 1 && self.item_order <= 42;
+
+        if check {
+            Ok(())
+        } else {
+            Err(ServiceError::BadRequest("Invalid Order".into()))
+        }
+    }
+}
+
 // as msg in rut get collect info
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct QueryCollect {
@@ -304,8 +345,8 @@ impl Message for QueryCollect {
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub enum QueryCollects {
     RutID(String),
-    ItemID(String, i32), // id, paging
-    UserID(String, i32), // id, paging
+    ItemID(String, Paging), // id, paging
+    UserID(String, Paging), // id, paging
 }
 
 impl Message for QueryCollects {
@@ -348,3 +389,170 @@ pub struct StarItemStatus {
 impl Message for StarItemStatus {
     type Result = Result<StarItemMsg, ServiceError>;
 }
+
+// old slug of merged item, redirect to the surviving one
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Identifiable, Queryable, Insertable)]
+#[table_name = "itemredirects"]
+#[primary_key(old_slug)]
+pub struct ItemRedirect {
+    pub old_slug: String,
+    pub old_id: String,
+    pub item_id: String,
+    pub uname: String,
+    pub create_at: NaiveDateTime,
+}
+
+// ItemRedirect's constructor
+impl ItemRedirect {
+    pub fn new(old: &Item, to_id: String, uname: String) -> Self {
+        ItemRedirect {
+            old_slug: old.slug.clone(),
+            old_id: old.id.clone(),
+            item_id: to_id,
+            uname,
+            create_at: Utc::now().naive_utc(),
+        }
+    }
+}
+
+// as msg to merge a duplicate item into another, by moderator
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct MergeItem {
+    pub from: String, // item id, to be removed
+    pub to: String,   // item id, surviving
+    #[serde(default)]
+    pub uname: String,
+}
+
+impl Message for MergeItem {
+    type Result = Result<ItemMsg, ServiceError>;
+}
+
+impl Validate for MergeItem {
+    fn validate(&self) -> Result<(), ServiceError> {
+        let check = test_len_limit(&self.from, 8, 512)
+            && test_len_limit(&self.to, 8, 512)
+            && self.from != self.to;
+
+        if check {
+            Ok(())
+        } else {
+            Err(ServiceError::BadRequest("Invalid Input".into()))
+        }
+    }
+}
+
+// a group of likely duplicate items, per normalized uiid or title+authors
+#[derive(Clone, Debug, Serialize, Deserialize, QueryableByName)]
+pub struct DupItems {
+    #[sql_type = "Text"]
+    pub per: String, // uiid|title
+    #[sql_type = "Text"]
+    pub dkey: String, // the normalized key
+    #[sql_type = "Array<Text>"]
+    pub ids: Vec<String>, // most collected first
+    #[sql_type = "Array<Text>"]
+    pub titles: Vec<String>,
+    #[sql_type = "Array<Text>"]
+    pub slugs: Vec<String>,
+    #[sql_type = "BigInt"]
+    pub count: i64,
+}
+
+// as msg to list likely duplicate items
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct QueryDupItems {
+    pub paging: Paging,
+}
+
+impl Message for QueryDupItems {
+    type Result = Result<DupItemsMsg, ServiceError>;
+}
+
+// some const for link check
+pub const LINK_CHECK_TICK: u64 = 5; // seconds, check an item per tick
+pub const LINK_CHECK_BATCH: i64 = 20; // items to load per batch
+pub const LINK_CHECK_AGE: i64 = 7; // days, re-check after
+
+// if a link is dead per the status: unreachable, or 4xx|5xx but 429
+pub fn is_dead_status(st: i16) -> bool {
+    st == -1 || (st >= 400 && st != 429)
+}
+
+// as msg from bot::linkcheck, to load items due to check
+#[derive(Debug, Clone)]
+pub struct QueryLinkCheck {
+    pub limit: i64,
+}
+
+impl Message for QueryLinkCheck {
+    type Result = Result<Vec<Item>, ServiceError>;
+}
+
+// as msg from bot::linkcheck, to save the status and suggest per draft
+#[derive(Debug, Clone)]
+pub struct SaveLinkCheck {
+    pub item_id: String,
+    pub url_status: i16, // 0 if not checked, e.g. per robots.txt
+    pub cover_status: i16,
+    pub draft: Option<NewItem>, // parsed from url if alive
+}
+
+impl Message for SaveLinkCheck {
+    type Result = Result<(), ServiceError>;
+}
+
+// metadata update suggested per link check
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Identifiable, Queryable, Insertable)]
+#[table_name = "itemsuggests"]
+pub struct ItemSuggest {
+    pub id: String,
+    pub item_id: String,
+    pub field: String, // uiid|authors|pub_at|publisher|cover|edition
+    pub old_value: String,
+    pub new_value: String,
+    pub status: String, // pending|approved|rejected
+    pub uname: String,  // moderator who decide
+    pub create_at: NaiveDateTime,
+    pub decide_at: Option<NaiveDateTime>,
+}
+
+// ItemSuggest's constructor
+impl ItemSuggest {
+    pub fn new(item_id: &str, field: &str, old_value: &str, new_value: &str) -> Self {
+        ItemSuggest {
+            id: format!("{}", uuid::Uuid::new_v4()),
+            item_id: item_id.to_owned(),
+            field: field.to_owned(),
+            old_value: old_value.to_owned(),
+            new_value: new_value.to_owned(),
+            status: "pending".to_owned(),
+            uname: "".to_owned(),
+            create_at: Utc::now().naive_utc(),
+            decide_at: None,
+        }
+    }
+}
+
+// as msg to list suggests per status
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct QuerySuggests {
+    pub status: String,
+    pub paging: Paging,
+}
+
+impl Message for QuerySuggests {
+    type Result = Result<SuggestListMsg, ServiceError>;
+}
+
+// as msg to approve 1 or reject 0 a suggest, by moderator
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct DecideSuggest {
+    pub id: String,
+    pub action: u8,
+    pub uname: String,
+}
+
+impl Message for DecideSuggest {
+    type Result = Result<ItemMsg, ServiceError>;
+}
@@ -1,18 +1,20 @@
 // type model mod
 
+pub mod crawl;
 pub mod etc;
 pub mod item;
 pub mod msg;
 pub mod rut;
+pub mod search;
 pub mod tag;
 pub mod user;
 
-use actix_web::Error;
+use crate::errors::ServiceError;
 use regex::Regex;
 
 // for validate request content input
 pub trait Validate {
-    fn validate(&self) -> Result<(), Error>;
+    fn validate(&self) -> Result<(), ServiceError>;
 }
 
 // re test
@@ -43,6 +45,15 @@ pub fn re_test_url(text: &str) -> bool {
     RE.is_match(text)
 }
 
+// for re test email
+pub fn re_test_email(text: &str) -> bool {
+    lazy_static! {
+        static ref RE: Regex =
+            Regex::new(r"^[\w.+-]+@[\w-]+(\.[\w-]+)+$").unwrap(); // let fail in test
+    }
+    RE.is_match(text)
+}
+
 pub fn re_test_img_url(text: &str) -> bool {
     lazy_static! {
         static ref RE: Regex =
@@ -74,6 +85,170 @@ pub fn trim_url_qry(text: &str, rep: &str) -> String {
     RE.replace_all(text, rep).into_owned()
 }
 
+// typed item identifier: isbn|doi|arxiv|asin, canonical form
+// isbn as isbn-13, doi lowercase w/o resolver, arxiv w/o version, asin uppercase
+pub fn normalize_uiid(text: &str) -> Result<String, ServiceError> {
+    lazy_static! {
+        static ref RE_PRE: Regex = Regex::new(
+            r"^(https?://(dx\.)?doi\.org/|doi:|https?://arxiv\.org/abs/|arxiv:|isbn(-?1[03])?:?)\s*"
+        ).unwrap(); // let fail in test
+        static ref RE_VER: Regex = Regex::new(r"v[0-9]+$").unwrap();
+    }
+    let low = text.trim().to_lowercase();
+    let id = RE_PRE.replace(&low, "").into_owned();
+    if id == "" {
+        return Ok(id);
+    }
+    if re_test_doi(&id) {
+        return Ok(id);
+    }
+    if re_test_arxiv(&id) {
+        return Ok(RE_VER.replace(&id, "").into_owned());
+    }
+
+    let bare = replace_sep(&id, "").to_uppercase();
+    if re_test_isbn10(&bare) {
+        return match isbn10_to_13(&bare) {
+            Some(isbn) => Ok(isbn),
+            None => Err(ServiceError::BadRequest("Invalid ISBN".into())),
+        };
+    }
+    if re_test_isbn13(&bare) {
+        return if isbn13_check(&bare) {
+            Ok(bare)
+        } else {
+            Err(ServiceError::BadRequest("Invalid ISBN".into()))
+        };
+    }
+    if re_test_asin(&bare) {
+        return Ok(bare);
+    }
+
+    // not a known type, just strip the separators, keep the case
+    Ok(replace_sep(text.trim(), ""))
+}
+
+// type of a canonical uiid, "" if unknown
+pub fn uiid_kind(uiid: &str) -> &'static str {
+    if re_test_isbn13(uiid) && isbn13_check(uiid) {
+        "isbn"
+    } else if re_test_doi(uiid) {
+        "doi"
+    } else if re_test_arxiv(uiid) {
+        "arxiv"
+    } else if re_test_asin(uiid) {
+        "asin"
+    } else {
+        ""
+    }
+}
+
+// the forms an identifier may have been saved in, to match duplicates
+pub fn uiid_variants(uiid: &str) -> Vec<String> {
+    let mut vars = vec![uiid.to_owned()];
+    match uiid_kind(uiid) {
+        "isbn" => {
+            if let Some(isbn10) = isbn13_to_10(uiid) {
+                vars.push(isbn10);
+            }
+        }
+        "doi" | "arxiv" => vars.push(replace_sep(uiid, "")),
+        _ => {}
+    }
+    vars
+}
+
+// isbn-10 weight 10..1, X as 10, sum mod 11
+pub fn isbn10_check(isbn: &str) -> bool {
+    let sum: u32 = isbn
+        .chars()
+        .zip((1..=10).rev())
+        .map(|(c, w)| if c == 'X' { 10 * w } else { c.to_digit(10).unwrap_or(0) * w })
+        .sum();
+    re_test_isbn10(isbn) && sum % 11 == 0
+}
+
+// isbn-13 weight 1,3 alternately, sum mod 10
+pub fn isbn13_check(isbn: &str) -> bool {
+    re_test_isbn13(isbn) && isbn13_sum(isbn) % 10 == 0
+}
+
+fn isbn13_sum(digits: &str) -> u32 {
+    digits
+        .chars()
+        .zip([1u32, 3].iter().cycle())
+        .map(|(c, w)| c.to_digit(10).unwrap_or(0) * w)
+        .sum()
+}
+
+// None if the isbn-10 checksum fails
+pub fn isbn10_to_13(isbn: &str) -> Option<String> {
+    if !isbn10_check(isbn) {
+        return None;
+    }
+    let body = format!("978{}", &isbn[..9]);
+    let check = (10 - isbn13_sum(&body) % 10) % 10;
+    Some(format!("{}{}", body, check))
+}
+
+// only 978 prefix has an isbn-10
+pub fn isbn13_to_10(isbn: &str) -> Option<String> {
+    if !isbn13_check(isbn) || !isbn.starts_with("978") {
+        return None;
+    }
+    let body = &isbn[3..12];
+    let sum: u32 = body
+        .chars()
+        .zip((2..=10).rev())
+        .map(|(c, w)| c.to_digit(10).unwrap_or(0) * w)
+        .sum();
+    let check = match (11 - sum % 11) % 11 {
+        10 => "X".to_owned(),
+        n => n.to_string(),
+    };
+    Some(format!("{}{}", body, check))
+}
+
+pub fn re_test_isbn10(text: &str) -> bool {
+    lazy_static! {
+        static ref RE: Regex = Regex::new(r"^[0-9]{9}[0-9X]$").unwrap(); // let fail in test
+    }
+    RE.is_match(text)
+}
+
+pub fn re_test_isbn13(text: &str) -> bool {
+    lazy_static! {
+        static ref RE: Regex = Regex::new(r"^97[89][0-9]{10}$").unwrap(); // let fail in test
+    }
+    RE.is_match(text)
+}
+
+// lowercase, w/o doi: or resolver
+pub fn re_test_doi(text: &str) -> bool {
+    lazy_static! {
+        static ref RE: Regex = Regex::new(r"^10\.[0-9]{4,9}/\S+$").unwrap(); // let fail in test
+    }
+    RE.is_match(text)
+}
+
+// new style 1706.03762, old style hep-th/9901001, lowercase
+pub fn re_test_arxiv(text: &str) -> bool {
+    lazy_static! {
+        static ref RE: Regex = Regex::new(
+            r"^([0-9]{4}\.[0-9]{4,5}|[a-z-]+(\.[a-z]{2})?/[0-9]{7})(v[0-9]+)?$"
+        ).unwrap(); // let fail in test
+    }
+    RE.is_match(text)
+}
+
+// amazon id of non-book, book asin is isbn-10
+pub fn re_test_asin(text: &str) -> bool {
+    lazy_static! {
+        static ref RE: Regex = Regex::new(r"^B0[0-9A-Z]{8}$").unwrap(); // let fail in test
+    }
+    RE.is_match(text)
+}
+
 pub fn test_len_limit(text: &str, min: usize, max: usize) -> bool {
     let l = text.trim().len();
     l >= min && l <= max
@@ -81,10 +256,136 @@ pub fn test_len_limit(text: &str, min: usize, max: usize) -> bool {
 
 // some const to eliminate magic number
 pub const PER_PAGE: i32 = 20; // for paging
+pub const MAX_PER_PAGE: i32 = 50; // upper bound of page size
+pub const TREE_DEPTH: i32 = 8; // max depth of reply tree
+pub const TAG_DEPTH: usize = 16; // max depth of tag hierarchy
 pub const TITLE_LEN: usize = 256;
 pub const URL_LEN: usize = 256;
-pub const UIID_LEN: usize = 32;
+pub const UIID_LEN: usize = 128; // doi may be long
 pub const TAG_LEN: usize = 42;
 pub const ST_LEN: usize = 16; // for some short input: category
 pub const MID_LEN: usize = 32; // for some mid input: lcoation
 pub const LG_LEN: usize = 64; // for sone longer input:
+
+// paging per page number, or per keyset cursor which takes priority
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct Paging {
+    pub page: i32,
+    pub cursor: String, // "" if not per cursor
+    pub size: i32,      // page size
+}
+
+impl Paging {
+    pub fn new(page: i32, cursor: Option<String>, size: Option<i32>) -> Self {
+        let size = match size {
+            Some(s) if s > 0 => std::cmp::min(s, MAX_PER_PAGE),
+            _ => PER_PAGE,
+        };
+        Paging {
+            page: std::cmp::max(page, 1),
+            cursor: cursor.unwrap_or_default().trim().to_owned(),
+            size,
+        }
+    }
+
+    pub fn limit(&self) -> i64 {
+        self.size.into()
+    }
+
+    // no offset if per cursor
+    pub fn offset(&self) -> i64 {
+        if self.cursor == "" {
+            i64::from(self.size) * (i64::from(self.page) - 1)
+        } else {
+            0
+        }
+    }
+
+    // next cursor if the page is full
+    pub fn is_full(&self, len: usize) -> bool {
+        len == self.size as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn isbn_checksums() {
+        assert!(isbn10_check("0262033844"));
+        assert!(isbn10_check("080442957X"));
+        assert!(!isbn10_check("0262033845"));
+        assert!(!isbn10_check("026203384"));
+        assert!(isbn13_check("9780262033848"));
+        assert!(isbn13_check("9780804429573"));
+        assert!(!isbn13_check("9780262033847"));
+        assert!(!isbn13_check("1234567890123"));
+    }
+
+    #[test]
+    fn isbn_conversions() {
+        assert_eq!(isbn10_to_13("0262033844"), Some("9780262033848".to_owned()));
+        assert_eq!(isbn10_to_13("080442957X"), Some("9780804429573".to_owned()));
+        assert_eq!(isbn10_to_13("0262033845"), None);
+        assert_eq!(isbn13_to_10("9780262033848"), Some("0262033844".to_owned()));
+        // X as the check digit
+        assert_eq!(isbn13_to_10("9780804429573"), Some("080442957X".to_owned()));
+        assert_eq!(isbn13_to_10("9780262033847"), None);
+        // no isbn-10 for 979
+        assert_eq!(isbn13_to_10("9791032305690"), None);
+    }
+
+    #[test]
+    fn normalize_isbn() {
+        assert_eq!(normalize_uiid("0-262-03384-4").unwrap(), "9780262033848");
+        assert_eq!(
+            normalize_uiid("ISBN 0-8044-2957-x").unwrap(),
+            "9780804429573"
+        );
+        assert_eq!(
+            normalize_uiid("isbn-13: 978-0-262-03384-8").unwrap(),
+            "9780262033848"
+        );
+        assert!(normalize_uiid("978-0-262-03384-7").is_err());
+        assert!(normalize_uiid("0262033845").is_err());
+        assert_eq!(normalize_uiid("  ").unwrap(), "");
+    }
+
+    #[test]
+    fn normalize_doi_arxiv() {
+        assert_eq!(
+            normalize_uiid("https://doi.org/10.1145/3368089.ABC").unwrap(),
+            "10.1145/3368089.abc"
+        );
+        assert_eq!(
+            normalize_uiid("http://dx.doi.org/10.1000/xyz").unwrap(),
+            "10.1000/xyz"
+        );
+        assert_eq!(normalize_uiid("DOI:10.1000/XYZ").unwrap(), "10.1000/xyz");
+        assert_eq!(normalize_uiid("arXiv:1706.03762v5").unwrap(), "1706.03762");
+        assert_eq!(
+            normalize_uiid("https://arxiv.org/abs/1706.03762").unwrap(),
+            "1706.03762"
+        );
+        assert_eq!(
+            normalize_uiid("hep-th/9901001v2").unwrap(),
+            "hep-th/9901001"
+        );
+        assert_eq!(normalize_uiid("b00abcdefg").unwrap(), "B00ABCDEFG");
+    }
+
+    #[test]
+    fn uiid_kinds_and_variants() {
+        assert_eq!(uiid_kind("9780262033848"), "isbn");
+        assert_eq!(uiid_kind("10.1000/xyz"), "doi");
+        assert_eq!(uiid_kind("1706.03762"), "arxiv");
+        assert_eq!(uiid_kind("B00ABCDEFG"), "asin");
+        assert_eq!(uiid_kind("CS229"), "");
+        assert_eq!(
+            uiid_variants("9780262033848"),
+            vec!["9780262033848", "0262033844"]
+        );
+        assert_eq!(uiid_variants("9791032305690"), vec!["9791032305690"]);
+    }
+}
@@ -1,10 +1,14 @@
 // typed-msg  model
 
-use crate::model::etc::Etc;
-use crate::model::item::{Collect, Item};
+use serde::Serialize;
+
+use crate::model::crawl::CrawlJob;
+use crate::model::etc::{Etc, EtcNode};
+use crate::model::item::{Collect, DupItems, Item, ItemSuggest};
 use crate::model::rut::Rut;
+use crate::model::search::{SearchFacet, SearchHit};
 use crate::model::tag::Tag;
-use crate::model::user::{CheckUser, User};
+use crate::model::user::{CheckUser, Follow, Timeline, User};
 
 // general response msg struct
 #[derive(Deserialize, Serialize, Debug)]
@@ -19,7 +23,8 @@ pub struct AuthMsg {
     pub status: i32,
     pub message: String,
     pub token: String,
-    pub exp: i32,
+    pub refresh: String, // "" if not renewed
+    pub exp: i32,        // access token, unit: minute
     pub user: CheckUser,
 }
 
@@ -31,6 +36,24 @@ pub struct UserMsg {
     pub user: CheckUser,
 }
 
+// result struct in response follower or following list
+#[derive(Deserialize, Serialize, Debug)]
+pub struct FollowListMsg {
+    pub status: i32,
+    pub message: String,
+    pub follows: Vec<Follow>,
+    pub count: usize, // total
+}
+
+// result struct in response activity feed
+#[derive(Deserialize, Serialize, Debug)]
+pub struct TimelineMsg {
+    pub status: i32,
+    pub message: String,
+    pub timelines: Vec<Timeline>,
+    pub next_cursor: String, // "" if no more
+}
+
 // result struct in response a rut
 #[derive(Deserialize, Serialize, Debug)]
 pub struct RutMsg {
@@ -46,6 +69,7 @@ pub struct RutListMsg {
     pub message: String,
     pub ruts: Vec<Rut>,
     pub count: usize,
+    pub next_cursor: String, // "" if no more
 }
 
 // result struct in response an item
@@ -63,6 +87,26 @@ pub struct ItemListMsg {
     pub message: String,
     pub items: Vec<Item>,
     pub count: usize,
+    pub next_cursor: String, // "" if no more
+}
+
+// result struct in response likely duplicate items
+#[derive(Deserialize, Serialize, Debug)]
+pub struct DupItemsMsg {
+    pub status: i32,
+    pub message: String,
+    pub dups: Vec<DupItems>,
+    pub count: usize,
+}
+
+// result struct in response item suggests
+#[derive(Deserialize, Serialize, Debug)]
+pub struct SuggestListMsg {
+    pub status: i32,
+    pub message: String,
+    pub suggests: Vec<ItemSuggest>,
+    pub count: usize,
+    pub next_cursor: String, // "" if no more
 }
 
 // result struct in respon the status of star item
@@ -88,6 +132,7 @@ pub struct CollectsMsg {
     pub status: i32,
     pub message: String,
     pub collects: Vec<Collect>,
+    pub next_cursor: String, // "" if no more
 }
 
 // result struct in response tag
@@ -107,6 +152,15 @@ pub struct TagListMsg {
     pub count: usize,
 }
 
+// result struct in response tag ancestors or subtree
+#[derive(Deserialize, Serialize, Debug)]
+pub struct TagTreeMsg {
+    pub status: i32,
+    pub message: String,
+    pub tags: Vec<Tag>, // ancestor: root to parent; subtree: level by level
+    pub count: usize,
+}
+
 // result struct in response etc
 #[derive(Deserialize, Serialize, Debug)]
 pub struct EtcMsg {
@@ -122,6 +176,26 @@ pub struct EtcListMsg {
     pub message: String,
     pub etcs: Vec<Etc>,
     pub count: usize,
+    pub next_cursor: String, // "" if no more
+}
+
+// result struct in response etc reply tree
+#[derive(Deserialize, Serialize, Debug)]
+pub struct EtcTreeMsg {
+    pub status: i32,
+    pub message: String,
+    pub etcs: Vec<EtcNode>,
+    pub count: usize,
+}
+
+// result struct in response search
+#[derive(Deserialize, Serialize, Debug)]
+pub struct SearchMsg {
+    pub status: i32,
+    pub message: String,
+    pub hits: Vec<SearchHit>,
+    pub facets: Vec<SearchFacet>,
+    pub count: usize, // total hits of all types
 }
 
 // todo
@@ -132,3 +206,42 @@ pub struct StarStatusMsg {
     pub message: String, // star | Unstar
     pub count: i32,      // star num
 }
+
+// msg for user's permission
+#[derive(Deserialize, Serialize, Debug)]
+pub struct PermitMsg {
+    pub status: i32,
+    pub message: String,
+    pub uname: String,
+    pub permission: i16,
+}
+
+// result struct in response crawl job, draft in job once done
+#[derive(Deserialize, Serialize, Debug)]
+pub struct CrawlJobMsg {
+    pub status: i32,
+    pub message: String,
+    pub job: CrawlJob,
+}
+
+// msg with status, to respond with the same http status
+pub trait StatusMsg: Serialize {
+    fn status(&self) -> i32;
+}
+
+macro_rules! impl_status_msg {
+    ($($msg:ty),*) => {
+        $(impl StatusMsg for $msg {
+            fn status(&self) -> i32 {
+                self.status
+            }
+        })*
+    };
+}
+
+impl_status_msg!(
+    Msg, AuthMsg, UserMsg, FollowListMsg, TimelineMsg, RutMsg, RutListMsg, ItemMsg,
+    ItemListMsg, StarItemMsg, CollectMsg, CollectsMsg, TagMsg, TagListMsg, TagTreeMsg, EtcMsg,
+    EtcListMsg, EtcTreeMsg, SearchMsg, StarStatusMsg, PermitMsg, DupItemsMsg, CrawlJobMsg,
+    SuggestListMsg
+);
@@ -1,12 +1,11 @@
 // rut typed model and msg handler
 
 use actix::Message;
-use actix_web::{error, Error};
 use chrono::{NaiveDateTime, Utc};
 
 use crate::errors::ServiceError;
 use crate::model::msg::{Msg, RutListMsg, RutMsg, StarStatusMsg};
-use crate::model::{re_test_url, test_len_limit, Validate, TITLE_LEN};
+use crate::model::{re_test_url, test_len_limit, Paging, Validate, TITLE_LEN};
 use crate::schema::{ruts, starruts};
 
 // use to build select query
@@ -69,7 +68,7 @@ impl Message for CreateRut {
 }
 
 impl Validate for CreateRut {
-    fn validate(&self) -> Result<(), Error> {
+    fn validate(&self) -> Result<(), ServiceError> {
         let url = &self.url.trim();
         let url_test = if url.len() == 0 {
             true
@@ -84,7 +83,7 @@ impl Validate for CreateRut {
         if check {
             Ok(())
         } else {
-            Err(error::ErrorBadRequest("Invalid Input"))
+            Err(ServiceError::BadRequest("Invalid Input".into()))
         }
     }
 }
@@ -104,10 +103,10 @@ impl Message for QueryRut {
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub enum QueryRuts {
     Index(String),
-    UserID(String, String, i32), // uname, create|star, paging
-    ItemID(String, i32),
-    TagID(String, i32),
-    KeyID(String, String, String, i32), // keyword, per, perid(uname|item|tname), paging
+    UserID(String, String, Paging), // uname, create|star, paging
+    ItemID(String, Paging),
+    TagID(String, bool, Paging), // tname, include descendant tags, paging
+    KeyID(String, String, String, Paging), // keyword, per, perid(uname|item|tname), paging
 }
 
 impl Message for QueryRuts {
@@ -132,7 +131,7 @@ impl Message for UpdateRut {
 }
 
 impl Validate for UpdateRut {
-    fn validate(&self) -> Result<(), Error> {
+    fn validate(&self) -> Result<(), ServiceError> {
         let url = &self.url.trim();
         let url_test = if url.len() == 0 {
             true
@@ -147,7 +146,7 @@ impl Validate for UpdateRut {
         if check {
             Ok(())
         } else {
-            Err(error::ErrorBadRequest("Invalid Input"))
+            Err(ServiceError::BadRequest("Invalid Input".into()))
         }
     }
 }
@@ -185,3 +184,14 @@ pub struct StarRutStatus {
 impl Message for StarRutStatus {
     type Result = Result<StarStatusMsg, ServiceError>;
 }
+
+// as msg to delete rut
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct DelRut {
+    pub rut_slug: String,
+    pub uname: String, // to check permission
+}
+
+impl Message for DelRut {
+    type Result = Result<Msg, ServiceError>;
+}
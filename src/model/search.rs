@@ -0,0 +1,101 @@
+// search typed model and msg
+
+use actix::Message;
+use diesel::sql_types::{BigInt, Float, Text};
+
+use crate::errors::ServiceError;
+use crate::model::msg::SearchMsg;
+use crate::model::{test_len_limit, Validate};
+
+// a ranked hit in search result, rut|item|tag|etc
+#[derive(Clone, Debug, Serialize, Deserialize, QueryableByName)]
+pub struct SearchHit {
+    #[sql_type = "Text"]
+    pub obj: String, // rut|item|tag|etc
+    #[sql_type = "Text"]
+    pub id: String,
+    #[sql_type = "Text"]
+    pub title: String,
+    #[sql_type = "Text"]
+    pub slug: String, // to build link, tname for tag
+    #[sql_type = "Text"]
+    pub snippet: String, // highlighted as <mark>
+    #[sql_type = "Float"]
+    pub rank: f32,
+}
+
+// hit count per type
+#[derive(Clone, Debug, Serialize, Deserialize, QueryableByName)]
+pub struct SearchFacet {
+    #[sql_type = "Text"]
+    pub obj: String,
+    #[sql_type = "BigInt"]
+    pub count: i64,
+}
+
+// a row of search query, a hit or a facet per kind
+#[derive(Clone, Debug, QueryableByName)]
+pub struct SearchRow {
+    #[sql_type = "Text"]
+    pub kind: String, // hit|facet
+    #[sql_type = "Text"]
+    pub obj: String,
+    #[sql_type = "Text"]
+    pub id: String,
+    #[sql_type = "Text"]
+    pub title: String,
+    #[sql_type = "Text"]
+    pub slug: String,
+    #[sql_type = "Text"]
+    pub snippet: String,
+    #[sql_type = "Float"]
+    pub rank: f32,
+    #[sql_type = "BigInt"]
+    pub count: i64,
+}
+
+impl SearchRow {
+    pub fn into_hit(self) -> SearchHit {
+        SearchHit {
+            obj: self.obj,
+            id: self.id,
+            title: self.title,
+            slug: self.slug,
+            snippet: self.snippet,
+            rank: self.rank,
+        }
+    }
+
+    pub fn into_facet(self) -> SearchFacet {
+        SearchFacet {
+            obj: self.obj,
+            count: self.count,
+        }
+    }
+}
+
+// as msg to full-text search
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct Search {
+    pub kw: String,
+    pub ty: String, // rut|item|tag|etc, or "" for all
+    pub page: i32,
+}
+
+impl Message for Search {
+    type Result = Result<SearchMsg, ServiceError>;
+}
+
+impl Validate for Search {
+    fn validate(&self) -> Result<(), ServiceError> {
+        let ty = self.ty.trim();
+        let check = test_len_limit(&self.kw, 1, 128)
+            && (ty == "" || ty == "rut" || ty == "item" || ty == "tag" || ty == "etc");
+
+        if check {
+            Ok(())
+        } else {
+            Err(ServiceError::BadRequest("Invalid Input".into()))
+        }
+    }
+}
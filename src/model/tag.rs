@@ -2,13 +2,13 @@
 
 use chrono::Utc;
 use actix::Message;
-use actix_web::{error, Error};
 use chrono::NaiveDateTime;
+use diesel::sql_types::{BigInt, Text};
 
 use crate::errors::ServiceError;
-use crate::model::msg::{Msg, StarStatusMsg, TagListMsg, TagMsg};
+use crate::model::msg::{Msg, StarStatusMsg, TagListMsg, TagMsg, TagTreeMsg};
 use crate::model::{re_test_url, test_len_limit, Validate, TAG_LEN};
-use crate::schema::{startags, tagetcs, tagitems, tagruts, tags};
+use crate::schema::{startags, tagaliases, tagetcs, tagitems, tagruts, tags};
 
 // use to build select query
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Identifiable, Queryable, Insertable)]
@@ -56,13 +56,13 @@ impl Message for CheckTag {
 }
 
 impl Validate for CheckTag {
-    fn validate(&self) -> Result<(), Error> {
+    fn validate(&self) -> Result<(), ServiceError> {
         let check = test_len_limit(&self.tname, 1, TAG_LEN);
 
         if check {
             Ok(())
         } else {
-            Err(error::ErrorBadRequest("Invalid Input"))
+            Err(ServiceError::BadRequest("Invalid Input".into()))
         }
     }
 }
@@ -81,6 +81,17 @@ impl Message for QueryTags {
     type Result = Result<TagListMsg, ServiceError>;
 }
 
+// as msg to get tag ancestors (breadcrumb) or subtree
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct QueryTagTree {
+    pub tname: String,
+    pub per: String, // ancestor|subtree
+}
+
+impl Message for QueryTagTree {
+    type Result = Result<TagTreeMsg, ServiceError>;
+}
+
 // as msg in update tag
 #[derive(Deserialize, Serialize, Debug, Clone, AsChangeset)]
 #[table_name = "tags"]
@@ -96,7 +107,7 @@ impl Message for UpdateTag {
 }
 
 impl Validate for UpdateTag {
-    fn validate(&self) -> Result<(), Error> {
+    fn validate(&self) -> Result<(), ServiceError> {
         let url = &self.logo.trim();
         let url_test = if url.len() == 0 {
             true
@@ -110,7 +121,7 @@ impl Validate for UpdateTag {
         if check {
             Ok(())
         } else {
-            Err(error::ErrorBadRequest("Invalid Input"))
+            Err(ServiceError::BadRequest("Invalid Input".into()))
         }
     }
 }
@@ -151,7 +162,7 @@ impl Message for RutTag {
 }
 
 impl Validate for RutTag {
-    fn validate(&self) -> Result<(), Error> {
+    fn validate(&self) -> Result<(), ServiceError> {
         let tags = &self.tnames;
         let action = self.action;
         let check = tags.len() > 0 && (action == 0 || action == 1);
@@ -159,7 +170,7 @@ impl Validate for RutTag {
         if check {
             Ok(())
         } else {
-            Err(error::ErrorBadRequest("Invalid Input"))
+            Err(ServiceError::BadRequest("Invalid Input".into()))
         }
     }
 }
@@ -222,6 +233,21 @@ impl TagItem {
     }
 }
 
+// a rut or item per its count summed over tags, in listing per tag and descendants
+#[derive(Clone, Debug, QueryableByName)]
+pub struct TaggedRow {
+    #[sql_type = "Text"]
+    pub oid: String,
+    #[sql_type = "BigInt"]
+    pub count: i64,
+}
+
+#[derive(Clone, Debug, QueryableByName)]
+pub struct CountRow {
+    #[sql_type = "BigInt"]
+    pub count: i64,
+}
+
 // to do
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Identifiable, Queryable, Insertable)]
 #[table_name = "tagetcs"]
@@ -258,7 +284,7 @@ impl Message for TagAny {
 }
 
 impl Validate for TagAny {
-    fn validate(&self) -> Result<(), Error> {
+    fn validate(&self) -> Result<(), ServiceError> {
         let tags = &self.tnames;
         let action = self.action;
         let tag_to = &self.tag_to;
@@ -269,7 +295,98 @@ impl Validate for TagAny {
         if check {
             Ok(())
         } else {
-            Err(error::ErrorBadRequest("Invalid Input"))
+            Err(ServiceError::BadRequest("Invalid Input".into()))
+        }
+    }
+}
+
+// alias of tag, resolved to the canonical tag at tag time
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Identifiable, Queryable, Insertable)]
+#[table_name = "tagaliases"]
+#[primary_key(alias)]
+pub struct TagAlias {
+    pub alias: String,
+    pub tname: String,
+    pub uname: String,
+    pub merged: bool, // left by merge, as redirect
+    pub create_at: NaiveDateTime,
+}
+
+// TagAlias's constructor
+impl TagAlias {
+    pub fn new(alias: String, tname: String, uname: String, merged: bool) -> Self {
+        TagAlias {
+            alias,
+            tname,
+            uname,
+            merged,
+            create_at: Utc::now().naive_utc(),
+        }
+    }
+}
+
+// as msg to set or remove alias, by moderator
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct UpdateTagAlias {
+    pub alias: String,
+    pub tname: String,
+    pub action: u8, // set 1 or remove 0
+    #[serde(default)]
+    pub uname: String,
+}
+
+impl Message for UpdateTagAlias {
+    type Result = Result<Msg, ServiceError>;
+}
+
+impl Validate for UpdateTagAlias {
+    fn validate(&self) -> Result<(), ServiceError> {
+        let check = test_len_limit(&self.alias, 1, TAG_LEN)
+            && test_len_limit(&self.tname, 1, TAG_LEN)
+            && self.alias != self.tname
+            && self.action <= 1;
+
+        if check {
+            Ok(())
+        } else {
+            Err(ServiceError::BadRequest("Invalid Input".into()))
+        }
+    }
+}
+
+// as msg to get aliases of tag
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct QueryTagAliases {
+    pub tname: String,
+}
+
+impl Message for QueryTagAliases {
+    type Result = Result<TagListMsg, ServiceError>;
+}
+
+// as msg to merge tag into another, by moderator
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct MergeTag {
+    pub from: String,
+    pub to: String,
+    #[serde(default)]
+    pub uname: String,
+}
+
+impl Message for MergeTag {
+    type Result = Result<TagMsg, ServiceError>;
+}
+
+impl Validate for MergeTag {
+    fn validate(&self) -> Result<(), ServiceError> {
+        let check = test_len_limit(&self.from, 1, TAG_LEN)
+            && test_len_limit(&self.to, 1, TAG_LEN)
+            && self.from != self.to;
+
+        if check {
+            Ok(())
+        } else {
+            Err(ServiceError::BadRequest("Invalid Input".into()))
         }
     }
 }
@@ -1,15 +1,20 @@
 // typed model and handle user message
 
 use actix::Message;
-use actix_web::{dev::Payload, error, Error, FromRequest, HttpRequest};
+use actix_web::{dev::Payload, web::Data, FromRequest, HttpRequest};
+use futures::{future::result, Future};
 use chrono::{Duration, Local, NaiveDateTime, Utc};
 use jsonwebtoken::{decode, encode, Header, Validation};
 use std::convert::From;
 
 use crate::errors::ServiceError;
-use crate::model::msg::{AuthMsg, Msg};
-use crate::model::{re_test_name, re_test_psw, re_test_url, test_len_limit, Validate, MID_LEN};
-use crate::schema::{follows, timelines, users};
+use crate::model::msg::{AuthMsg, FollowListMsg, Msg, PermitMsg, StarStatusMsg, TimelineMsg};
+use crate::model::{
+    re_test_email, re_test_name, re_test_psw, re_test_url, test_len_limit, Validate, MID_LEN,
+};
+use crate::schema::{follows, pswresets, sessions, timelines, users};
+use crate::util::mail::SendMail;
+use crate::DbAddr;
 
 pub const LIMIT_PERMIT: i16 = 0x01;  // follow,star...
 pub const BASIC_PERMIT: i16 = 0x02;  // create, edit self created...
@@ -17,6 +22,9 @@ pub const EIDT_PERMIT: i16 = 0x04;   // edit/del others' creats
 pub const MOD_PERMIT: i16 = 0x10;    // mod role
 pub const ADMIN_PERMIT: i16 = 0x80;  // admin
 
+pub const ACCESS_EXP: i64 = 30;     // access token, unit: minute
+pub const REFRESH_EXP: i64 = 30;    // refresh token, unit: day
+
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Identifiable, Queryable, Insertable)]
 #[table_name = "users"]
@@ -74,6 +82,17 @@ pub struct CheckUser {
     pub intro: String,
     pub location: String,
     pub nickname: String,
+    #[serde(skip)]
+    pub sid: String, // session id in token, "" if not from token
+    #[serde(skip)]
+    pub permission: i16, // loaded when auth via session
+}
+
+impl CheckUser {
+    // check permission
+    pub fn can(&self, permission: i16) -> bool {
+        (self.permission & permission) == permission
+    }
 }
 
 impl From<User> for CheckUser {
@@ -87,6 +106,8 @@ impl From<User> for CheckUser {
             intro: user.intro,
             location: user.location,
             nickname: user.nickname,
+            sid: "".to_owned(),
+            permission: user.permission,
         }
     }
 }
@@ -95,20 +116,86 @@ impl Message for CheckUser {
     type Result = Result<Msg, ServiceError>;
 }
 
-// auth via token
+// auth via token, and the session must be alive
 impl FromRequest for CheckUser {
     type Config = ();
     type Error = ServiceError;
-    type Future = Result<CheckUser, ServiceError>;
-
-    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
-        if let Some(auth_token) = req.headers().get("authorization") {
-            if let Ok(auth) = auth_token.to_str() {
-                let user: CheckUser = decode_token(auth)?;
-                return Ok(user);
-            }
+    type Future = Box<dyn Future<Item = CheckUser, Error = ServiceError>>;
+
+    fn from_request(req: &HttpRequest, pl: &mut Payload) -> Self::Future {
+        let check_token = match req.headers().get("authorization") {
+            Some(auth_token) => match auth_token.to_str() {
+                Ok(auth) => decode_token(auth),
+                Err(_) => Err(ServiceError::Unauthorized),
+            },
+            None => Err(ServiceError::Unauthorized),
+        };
+        let db = Data::<DbAddr>::from_request(req, pl)
+            .map_err(|_| ServiceError::InternalServerError("db".into()));
+
+        Box::new(
+            result(check_token.and_then(|user| db.map(|d| (user, d)))).and_then(|(user, db)| {
+                let sid = user.sid.clone();
+                let uname = user.uname.clone();
+                db.send(CheckSession { sid, uname })
+                    .from_err()
+                    .and_then(|res| res.map(|permission| CheckUser { permission, ..user }))
+            }),
+        )
+    }
+}
+
+// auth and check the permission required
+fn check_permit(
+    req: &HttpRequest,
+    pl: &mut Payload,
+    permit: i16,
+) -> impl Future<Item = CheckUser, Error = ServiceError> {
+    CheckUser::from_request(req, pl).and_then(move |user| {
+        if user.can(permit) {
+            Ok(user)
+        } else {
+            Err(ServiceError::Forbidden)
         }
-        Err(ServiceError::Unauthorized.into())
+    })
+}
+
+// auth as editor, to edit/del others' creats
+pub struct CheckEditor(pub CheckUser);
+
+impl FromRequest for CheckEditor {
+    type Config = ();
+    type Error = ServiceError;
+    type Future = Box<dyn Future<Item = CheckEditor, Error = ServiceError>>;
+
+    fn from_request(req: &HttpRequest, pl: &mut Payload) -> Self::Future {
+        Box::new(check_permit(req, pl, EIDT_PERMIT).map(CheckEditor))
+    }
+}
+
+// auth as moderator
+pub struct CheckMod(pub CheckUser);
+
+impl FromRequest for CheckMod {
+    type Config = ();
+    type Error = ServiceError;
+    type Future = Box<dyn Future<Item = CheckMod, Error = ServiceError>>;
+
+    fn from_request(req: &HttpRequest, pl: &mut Payload) -> Self::Future {
+        Box::new(check_permit(req, pl, MOD_PERMIT).map(CheckMod))
+    }
+}
+
+// auth as admin
+pub struct CheckAdmin(pub CheckUser);
+
+impl FromRequest for CheckAdmin {
+    type Config = ();
+    type Error = ServiceError;
+    type Future = Box<dyn Future<Item = CheckAdmin, Error = ServiceError>>;
+
+    fn from_request(req: &HttpRequest, pl: &mut Payload) -> Self::Future {
+        Box::new(check_permit(req, pl, ADMIN_PERMIT).map(CheckAdmin))
     }
 }
 
@@ -121,18 +208,20 @@ pub struct Claims {
     pub exp: i64,    // expiry
     pub uid: String, // user id
     pub uname: String,
+    pub sid: String, // session id
 }
 
 // claims's constructor
 impl Claims {
-    pub fn new(uid: &str, uname: &str) -> Self {
+    pub fn new(uid: &str, uname: &str, sid: &str) -> Self {
         Claims {
             iss: "ruthub".into(),
             sub: "auth".into(),
             iat: Local::now().timestamp(),
-            exp: (Local::now() + Duration::hours(24 * 5)).timestamp(),
+            exp: (Local::now() + Duration::minutes(ACCESS_EXP)).timestamp(),
             uid: uid.to_owned(),
             uname: uname.to_owned(),
+            sid: sid.to_owned(),
         }
     }
 }
@@ -148,10 +237,106 @@ impl From<Claims> for CheckUser {
             intro: "".to_owned(),
             location: "".to_owned(),
             nickname: "".to_owned(),
+            sid: claims.sid,
+            permission: 0,
         }
     }
 }
 
+// signin session, per rotating refresh token
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Identifiable, Queryable, Insertable)]
+#[table_name = "sessions"]
+pub struct Session {
+    pub id: String,
+    pub uname: String,
+    pub refresh_hash: String,
+    pub expire_at: NaiveDateTime,
+    pub revoked: bool,
+    pub create_at: NaiveDateTime,
+    pub renew_at: NaiveDateTime,
+}
+
+// session id and plain refresh token, to client
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct SessionToken {
+    pub sid: String,
+    pub refresh: String, // sid.secret
+}
+
+// msg to get new access token per refresh token, rotate refresh token
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct RefreshSession {
+    pub refresh: String,
+}
+
+impl Message for RefreshSession {
+    type Result = Result<(CheckUser, SessionToken), ServiceError>;
+}
+
+// msg to sign out: revoke the session, or all sessions of user
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct RevokeSession {
+    pub uname: String,
+    pub sid: String,
+    pub all: bool,
+}
+
+impl Message for RevokeSession {
+    type Result = Result<Msg, ServiceError>;
+}
+
+// msg to check if session alive, in auth, get user's permission
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct CheckSession {
+    pub sid: String,
+    pub uname: String,
+}
+
+impl Message for CheckSession {
+    type Result = Result<i16, ServiceError>;
+}
+
+// msg to grant or revoke permission bits, by admin
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct UpdatePermit {
+    pub uname: String,
+    pub permit: i16,
+    pub action: u8, // 1: grant, 0: revoke
+    #[serde(default)]
+    pub by: String, // admin, set from auth
+}
+
+impl Message for UpdatePermit {
+    type Result = Result<PermitMsg, ServiceError>;
+}
+
+impl Validate for UpdatePermit {
+    fn validate(&self) -> Result<(), ServiceError> {
+        let all = LIMIT_PERMIT | BASIC_PERMIT | EIDT_PERMIT | MOD_PERMIT | ADMIN_PERMIT;
+        // no self change, avoid to lock out the last admin
+        let check = self.permit != 0
+            && (self.permit & !all) == 0
+            && self.action <= 1
+            && self.uname != self.by;
+
+        if check {
+            Ok(())
+        } else {
+            Err(ServiceError::BadRequest("Invalid Permission".into()))
+        }
+    }
+}
+
+// msg to get user's permission
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct QueryPermit {
+    pub uname: String,
+}
+
+impl Message for QueryPermit {
+    type Result = Result<PermitMsg, ServiceError>;
+}
+
 // message to sign up user
 #[derive(Deserialize, Serialize, Debug)]
 pub struct RegUser {
@@ -165,7 +350,7 @@ impl Message for RegUser {
 }
 
 impl Validate for RegUser {
-    fn validate(&self) -> Result<(), Error> {
+    fn validate(&self) -> Result<(), ServiceError> {
         let uname = &self.uname;
         let psw = &self.password;
         let check = re_test_name(uname) && re_test_psw(psw);
@@ -173,7 +358,7 @@ impl Validate for RegUser {
         if check {
             Ok(())
         } else {
-            Err(error::ErrorBadRequest("Invalid username or password"))
+            Err(ServiceError::BadRequest("Invalid username or password".into()))
         }
     }
 }
@@ -186,11 +371,11 @@ pub struct AuthUser {
 }
 
 impl Message for AuthUser {
-    type Result = Result<CheckUser, ServiceError>;
+    type Result = Result<(CheckUser, SessionToken), ServiceError>;
 }
 
 impl Validate for AuthUser {
-    fn validate(&self) -> Result<(), Error> {
+    fn validate(&self) -> Result<(), ServiceError> {
         let uname = &self.uname;
         let psw = &self.password;
         let check = test_len_limit(uname, 3, 42) && test_len_limit(psw, 8, 18);
@@ -198,7 +383,7 @@ impl Validate for AuthUser {
         if check {
             Ok(())
         } else {
-            Err(error::ErrorBadRequest("Invalid username or password"))
+            Err(ServiceError::BadRequest("Invalid username or password".into()))
         }
     }
 }
@@ -226,11 +411,11 @@ pub struct UpdateUser {
 }
 
 impl Message for UpdateUser {
-    type Result = Result<CheckUser, ServiceError>;
+    type Result = Result<(CheckUser, Option<SendMail>), ServiceError>;
 }
 
 impl Validate for UpdateUser {
-    fn validate(&self) -> Result<(), Error> {
+    fn validate(&self) -> Result<(), ServiceError> {
         let nickname = &self.nickname.trim();
         let nickname_test = if nickname.len() == 0 {
             true
@@ -243,23 +428,51 @@ impl Validate for UpdateUser {
         } else {
             re_test_url(avatar)
         };
+        let email = &self.email.trim();
+        let email_test = if email.len() == 0 {
+            true
+        } else {
+            re_test_email(email)
+        };
         let check_len = test_len_limit(&self.location, 0, MID_LEN);
-        let check = nickname_test && avatar_test && check_len;
+        let check = nickname_test && avatar_test && email_test && check_len;
 
         if check {
             Ok(())
         } else {
-            Err(error::ErrorBadRequest("Invalid Input"))
+            Err(ServiceError::BadRequest("Invalid Input".into()))
         }
     }
 }
 
+// msg to send a mail to confirm email
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct SendConfirmEmail {
+    pub uname: String,
+}
+
+impl Message for SendConfirmEmail {
+    type Result = Result<Msg, ServiceError>;
+}
+
+// msg to confirm email per token in mail
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ConfirmEmail {
+    pub token: String,
+}
+
+impl Message for ConfirmEmail {
+    type Result = Result<Msg, ServiceError>;
+}
+
 // msg to change psw
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct ChangePsw {
     pub old_psw: String,
     pub new_psw: String,
     pub uname: String,
+    #[serde(default)]
+    pub sid: String, // current session, keep it, revoke others
 }
 
 impl Message for ChangePsw {
@@ -267,20 +480,64 @@ impl Message for ChangePsw {
 }
 
 impl Validate for ChangePsw {
-    fn validate(&self) -> Result<(), Error> {
+    fn validate(&self) -> Result<(), ServiceError> {
         let check = re_test_psw(&self.new_psw);
 
         if check {
             Ok(())
         } else {
-            Err(error::ErrorBadRequest("Invalid Password"))
+            Err(ServiceError::BadRequest("Invalid Password".into()))
+        }
+    }
+}
+
+// token to reset password, single use
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Identifiable, Queryable, Insertable)]
+#[table_name = "pswresets"]
+pub struct PswReset {
+    pub id: String,
+    pub uname: String,
+    pub token_hash: String,
+    pub expire_at: NaiveDateTime,
+    pub used: bool,
+    pub create_at: NaiveDateTime,
+}
+
+// msg to request a mail with token to reset password
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ReqResetPsw {
+    pub uname: String,
+}
+
+impl Message for ReqResetPsw {
+    type Result = Result<Msg, ServiceError>;
+}
+
+// msg to reset password per token in mail
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ResetPsw {
+    pub token: String,
+    pub new_psw: String,
+}
+
+impl Message for ResetPsw {
+    type Result = Result<Msg, ServiceError>;
+}
+
+impl Validate for ResetPsw {
+    fn validate(&self) -> Result<(), ServiceError> {
+        let check = re_test_psw(&self.new_psw) && test_len_limit(&self.token, 1, 256);
+
+        if check {
+            Ok(())
+        } else {
+            Err(ServiceError::BadRequest("Invalid Password".into()))
         }
     }
 }
 
-// to do:
 // User follow
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Identifiable, Queryable)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Identifiable, Queryable, Insertable)]
 #[table_name = "follows"]
 pub struct Follow {
     pub id: String,
@@ -290,8 +547,55 @@ pub struct Follow {
     pub note: String,
 }
 
+// as msg in follow or unfollow user
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct FollowOrNot {
+    pub uname: String,
+    pub fname: String,
+    pub note: String,
+    pub action: u8, // 0- unfollow, 1- follow
+}
+
+impl Message for FollowOrNot {
+    type Result = Result<StarStatusMsg, ServiceError>;
+}
+
+impl Validate for FollowOrNot {
+    fn validate(&self) -> Result<(), ServiceError> {
+        let check = self.uname != self.fname && (self.action == 0 || self.action == 1);
+
+        if check {
+            Ok(())
+        } else {
+            Err(ServiceError::BadRequest("Cannot Follow Self".into()))
+        }
+    }
+}
+
+// as msg to check if follow a user
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct FollowStatus {
+    pub uname: String,
+    pub fname: String,
+}
+
+impl Message for FollowStatus {
+    type Result = Result<StarStatusMsg, ServiceError>;
+}
+
+// as msg to get follower or following list, + paging
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub enum QueryFollows {
+    Follower(String, i32),  // who follow uname, paging
+    Following(String, i32), // who uname follow, paging
+}
+
+impl Message for QueryFollows {
+    type Result = Result<FollowListMsg, ServiceError>;
+}
+
 // user's activity record
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Identifiable, Queryable)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Identifiable, Queryable, Insertable)]
 #[table_name = "timelines"]
 pub struct Timeline {
     pub id: String,
@@ -302,12 +606,61 @@ pub struct Timeline {
     pub act_at: NaiveDateTime, // when
 }
 
+// Timeline's constructor
+impl Timeline {
+    pub fn new(uname: &str, action: &str, obj: &str, objid: &str) -> Self {
+        Timeline {
+            id: format!("{}", uuid::Uuid::new_v4()),
+            uname: uname.to_owned(),
+            action: action.to_owned(),
+            obj: obj.to_owned(),
+            objid: objid.to_owned(),
+            act_at: Utc::now().naive_utc(),
+        }
+    }
+}
+
+// as msg to get activity feed, + cursor paging
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub enum QueryTimelines {
+    UserID(String, String), // uname, cursor: activity of a user
+    Home(String, String),   // uname, cursor: activity of who uname follow
+}
+
+impl Message for QueryTimelines {
+    type Result = Result<TimelineMsg, ServiceError>;
+}
+
+// jwt Token to confirm email: the email to confirm is signed in
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EmailClaims {
+    pub iss: String,
+    pub sub: String, // "email", to not be used as auth token
+    pub iat: i64,
+    pub exp: i64,
+    pub uname: String,
+    pub email: String,
+}
+
+impl EmailClaims {
+    pub fn new(uname: &str, email: &str) -> Self {
+        EmailClaims {
+            iss: "ruthub".into(),
+            sub: "email".into(),
+            iat: Local::now().timestamp(),
+            exp: (Local::now() + Duration::hours(24)).timestamp(),
+            uname: uname.to_owned(),
+            email: email.to_owned(),
+        }
+    }
+}
+
 fn get_secret() -> String {
     dotenv::var("SECRET_KEY").unwrap_or_else(|_| "AHaRdGuESsSeCREkY".into())
 }
 
-pub fn encode_token(data: &CheckUser) -> Result<String, ServiceError> {
-    let claims = Claims::new(data.id.as_str(), data.uname.as_str());
+pub fn encode_token(data: &CheckUser, sid: &str) -> Result<String, ServiceError> {
+    let claims = Claims::new(data.id.as_str(), data.uname.as_str(), sid);
     encode(&Header::default(), &claims, get_secret().as_ref())
         .map_err(|_err| ServiceError::InternalServerError("encode".into()))
 }
@@ -317,3 +670,19 @@ pub fn decode_token(token: &str) -> Result<CheckUser, ServiceError> {
         .map(|data| Ok(data.claims.into()))
         .map_err(|_err| ServiceError::Unauthorized)?
 }
+
+pub fn encode_email_token(uname: &str, email: &str) -> Result<String, ServiceError> {
+    let claims = EmailClaims::new(uname, email);
+    encode(&Header::default(), &claims, get_secret().as_ref())
+        .map_err(|_err| ServiceError::InternalServerError("encode".into()))
+}
+
+pub fn decode_email_token(token: &str) -> Result<EmailClaims, ServiceError> {
+    let validation = Validation {
+        sub: Some("email".into()),
+        ..Validation::default()
+    };
+    decode::<EmailClaims>(token, get_secret().as_ref(), &validation)
+        .map(|data| data.claims)
+        .map_err(|_err| ServiceError::BadRequest("Invalid or Expired Token".into()))
+}
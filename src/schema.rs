@@ -10,6 +10,22 @@ table! {
     }
 }
 
+table! {
+    crawl_jobs (id) {
+        id -> Varchar,
+        url -> Varchar,
+        domain -> Varchar,
+        uname -> Varchar,
+        status -> Varchar,
+        attempts -> Int2,
+        draft -> Jsonb,
+        error -> Varchar,
+        next_at -> Timestamp,
+        create_at -> Timestamp,
+        update_at -> Timestamp,
+    }
+}
+
 table! {
     etcs (id) {
         id -> Varchar,
@@ -52,6 +68,46 @@ table! {
         done_count -> Int4,
         vote -> Int4,
         slug -> Varchar,
+        url_status -> Int2,
+        cover_status -> Int2,
+        dead_link -> Bool,
+        checked_at -> Nullable<Timestamp>,
+        uiid_type -> Varchar,
+    }
+}
+
+table! {
+    itemredirects (old_slug) {
+        old_slug -> Varchar,
+        old_id -> Varchar,
+        item_id -> Varchar,
+        uname -> Varchar,
+        create_at -> Timestamp,
+    }
+}
+
+table! {
+    itemsuggests (id) {
+        id -> Varchar,
+        item_id -> Varchar,
+        field -> Varchar,
+        old_value -> Varchar,
+        new_value -> Varchar,
+        status -> Varchar,
+        uname -> Varchar,
+        create_at -> Timestamp,
+        decide_at -> Nullable<Timestamp>,
+    }
+}
+
+table! {
+    pswresets (id) {
+        id -> Varchar,
+        uname -> Varchar,
+        token_hash -> Varchar,
+        expire_at -> Timestamp,
+        used -> Bool,
+        create_at -> Timestamp,
     }
 }
 
@@ -75,6 +131,18 @@ table! {
     }
 }
 
+table! {
+    sessions (id) {
+        id -> Varchar,
+        uname -> Varchar,
+        refresh_hash -> Varchar,
+        expire_at -> Timestamp,
+        revoked -> Bool,
+        create_at -> Timestamp,
+        renew_at -> Timestamp,
+    }
+}
+
 table! {
     staritems (id) {
         id -> Varchar,
@@ -107,6 +175,16 @@ table! {
     }
 }
 
+table! {
+    tagaliases (alias) {
+        alias -> Varchar,
+        tname -> Varchar,
+        uname -> Varchar,
+        merged -> Bool,
+        create_at -> Timestamp,
+    }
+}
+
 table! {
     tagetcs (id) {
         id -> Varchar,
@@ -182,13 +260,19 @@ table! {
 
 allow_tables_to_appear_in_same_query!(
     collects,
+    crawl_jobs,
     etcs,
     follows,
+    itemredirects,
     items,
+    itemsuggests,
+    pswresets,
     ruts,
+    sessions,
     staritems,
     starruts,
     startags,
+    tagaliases,
     tagetcs,
     tagitems,
     tagruts,
@@ -0,0 +1,127 @@
+// mail sender, pluggable per env MAIL_BACKEND: smtp | outbox
+
+use actix::{Actor, Addr, Handler, Message, SyncArbiter, SyncContext};
+use lettre::smtp::authentication::Credentials;
+use lettre::{SmtpClient, Transport};
+use lettre_email::EmailBuilder;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::errors::ServiceError;
+
+pub trait MailSender {
+    fn send(&self, to: &str, subject: &str, body: &str) -> Result<(), ServiceError>;
+}
+
+// send via SMTP server
+pub struct SmtpSender {
+    pub host: String,
+    pub user: String,
+    pub password: String,
+    pub from: String,
+}
+
+impl MailSender for SmtpSender {
+    fn send(&self, to: &str, subject: &str, body: &str) -> Result<(), ServiceError> {
+        let email = EmailBuilder::new()
+            .to(to)
+            .from(self.from.as_str())
+            .subject(subject)
+            .text(body)
+            .build()
+            .map_err(|_| ServiceError::BadRequest("Invalid Email".into()))?;
+
+        let mut mailer = SmtpClient::new_simple(&self.host)
+            .map_err(|_| ServiceError::InternalServerError("smtp".into()))?
+            .credentials(Credentials::new(self.user.clone(), self.password.clone()))
+            .transport();
+        mailer
+            .send(email.into())
+            .map_err(|_| ServiceError::InternalServerError("smtp".into()))?;
+
+        Ok(())
+    }
+}
+
+// write mail as file to a local dir, for dev and test offline
+pub struct OutboxSender {
+    pub dir: PathBuf,
+}
+
+impl MailSender for OutboxSender {
+    fn send(&self, to: &str, subject: &str, body: &str) -> Result<(), ServiceError> {
+        fs::create_dir_all(&self.dir)
+            .map_err(|_| ServiceError::InternalServerError("outbox".into()))?;
+        // one file per mail, name: to-timestamp-uuid.eml
+        let fname = format!(
+            "{}-{}-{}.eml",
+            to,
+            chrono::Utc::now().timestamp(),
+            uuid::Uuid::new_v4()
+        );
+        let mail = format!("To: {}\nSubject: {}\n\n{}\n", to, subject, body);
+        fs::write(self.dir.join(fname), mail)
+            .map_err(|_| ServiceError::InternalServerError("outbox".into()))?;
+
+        Ok(())
+    }
+}
+
+// get the sender per env, default to outbox
+pub fn mail_sender() -> Box<dyn MailSender> {
+    match dotenv::var("MAIL_BACKEND").unwrap_or_default().as_str() {
+        "smtp" => Box::new(SmtpSender {
+            host: dotenv::var("SMTP_HOST").unwrap_or_else(|_| "localhost".into()),
+            user: dotenv::var("SMTP_USER").unwrap_or_default(),
+            password: dotenv::var("SMTP_PASSWORD").unwrap_or_default(),
+            from: dotenv::var("MAIL_FROM").unwrap_or_else(|_| "noreply@ruthub.com".into()),
+        }),
+        _ => Box::new(OutboxSender {
+            dir: PathBuf::from(dotenv::var("MAIL_OUTBOX").unwrap_or_else(|_| "outbox".into())),
+        }),
+    }
+}
+
+// the site url to build link in mail
+pub fn site_url() -> String {
+    dotenv::var("SITE_URL").unwrap_or_else(|_| "https://ruthub.com".into())
+}
+
+// mail executor actor, to send off the Dba, blocking io per thread
+pub struct Mailer;
+
+impl Actor for Mailer {
+    type Context = SyncContext<Self>;
+}
+
+pub type MailAddr = Addr<Mailer>;
+
+pub const MAILER_NUM: usize = 1; // threads of Mailer
+
+pub fn init_mailer() -> MailAddr {
+    SyncArbiter::start(MAILER_NUM, || Mailer)
+}
+
+// as msg to send a mail via Mailer
+#[derive(Debug, Clone)]
+pub struct SendMail {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+impl Message for SendMail {
+    type Result = Result<(), ServiceError>;
+}
+
+impl Handler<SendMail> for Mailer {
+    type Result = Result<(), ServiceError>;
+
+    fn handle(&mut self, m: SendMail, _: &mut Self::Context) -> Self::Result {
+        let res = mail_sender().send(&m.to, &m.subject, &m.body);
+        if let Err(ref e) = res {
+            warn!("mail to {}: {}", m.to, e);
+        }
+        res
+    }
+}
@@ -2,4 +2,5 @@
 
 // some helper
 
+pub mod mail;
 pub mod share;
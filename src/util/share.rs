@@ -1,10 +1,14 @@
 // some sharing helpers
 
 use base64;
+use chrono::NaiveDateTime;
 use deunicode::deunicode_char;
 use std::collections::HashMap;
+use std::str::FromStr;
 use uuid::Uuid;
 
+use crate::errors::ServiceError;
+
 pub fn to_blob(uuid: &Uuid) -> String {
     base64::encode_config(uuid.as_bytes(), base64::URL_SAFE_NO_PAD)
 }
@@ -73,3 +77,105 @@ pub fn get_v(map_ref: &HashMap<String, String>, k: &str) -> String {
     }
     return "".to_string();
 }
+
+// opaque cursor for keyset paging, per sort key and id
+pub fn encode_cursor(key: &str, id: &str) -> String {
+    base64::encode_config(format!("{}|{}", key, id).as_bytes(), base64::URL_SAFE_NO_PAD)
+}
+
+// decode cursor into (sort key, id)
+pub fn decode_cursor(cursor: &str) -> Result<(String, String), ServiceError> {
+    let bytes = base64::decode_config(cursor, base64::URL_SAFE_NO_PAD)?;
+    let text = String::from_utf8(bytes)
+        .map_err(|_| ServiceError::BadRequest("Invalid Cursor".into()))?;
+    let mut parts = text.splitn(2, '|');
+    match (parts.next(), parts.next()) {
+        (Some(k), Some(i)) => Ok((k.to_owned(), i.to_owned())),
+        _ => Err(ServiceError::BadRequest("Invalid Cursor".into())),
+    }
+}
+
+// time format of sort key in cursor
+pub const CURSOR_TIME_FMT: &str = "%Y-%m-%d %H:%M:%S%.f";
+
+// cursor per time sort key and id
+pub fn time_cursor(at: &NaiveDateTime, id: &str) -> String {
+    encode_cursor(&at.format(CURSOR_TIME_FMT).to_string(), id)
+}
+
+pub fn parse_time_cursor(cursor: &str) -> Result<(NaiveDateTime, String), ServiceError> {
+    let (k, i) = decode_cursor(cursor)?;
+    let at = NaiveDateTime::parse_from_str(&k, CURSOR_TIME_FMT)
+        .map_err(|_| ServiceError::BadRequest("Invalid Cursor".into()))?;
+    Ok((at, i))
+}
+
+// cursor per int sort key, like count, and id
+pub fn parse_int_cursor<N: FromStr>(cursor: &str) -> Result<(N, String), ServiceError> {
+    let (k, i) = decode_cursor(cursor)?;
+    let n = k
+        .parse::<N>()
+        .map_err(|_| ServiceError::BadRequest("Invalid Cursor".into()))?;
+    Ok((n, i))
+}
+
+// sort the loaded list per the order of id list, as `id = any(..)` loses order
+pub fn sort_by_ids<T, F>(list: &mut Vec<T>, ids: &[String], key: F)
+where
+    F: Fn(&T) -> &str,
+{
+    // first position wins if an id is duplicated, unknown ones go last
+    let mut pos: HashMap<&str, usize> = HashMap::new();
+    for (i, id) in ids.iter().enumerate() {
+        pos.entry(id.as_str()).or_insert(i);
+    }
+    list.sort_by_key(|t| pos.get(key(t)).cloned().unwrap_or(usize::max_value()));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(v: &[&str]) -> Vec<String> {
+        v.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn sort_by_ids_reordered() {
+        let mut list = ids(&["a", "b", "c"]);
+        sort_by_ids(&mut list, &ids(&["c", "a", "b"]), |s| s.as_str());
+        assert_eq!(list, ids(&["c", "a", "b"]));
+    }
+
+    #[test]
+    fn sort_by_ids_missing() {
+        // loaded but not in ids go last, keep their order
+        let mut list = ids(&["x", "b", "y", "a"]);
+        sort_by_ids(&mut list, &ids(&["a", "b"]), |s| s.as_str());
+        assert_eq!(list, ids(&["a", "b", "x", "y"]));
+        // in ids but not loaded, just skipped
+        let mut list = ids(&["b", "a"]);
+        sort_by_ids(&mut list, &ids(&["z", "a", "q", "b"]), |s| s.as_str());
+        assert_eq!(list, ids(&["a", "b"]));
+    }
+
+    #[test]
+    fn sort_by_ids_duplicate() {
+        // first position wins
+        let mut list = ids(&["a", "b", "c"]);
+        sort_by_ids(&mut list, &ids(&["b", "c", "b", "a"]), |s| s.as_str());
+        assert_eq!(list, ids(&["b", "c", "a"]));
+        // duplicated in list, stay together
+        let mut list = ids(&["a", "b", "a"]);
+        sort_by_ids(&mut list, &ids(&["b", "a"]), |s| s.as_str());
+        assert_eq!(list, ids(&["b", "a", "a"]));
+    }
+
+    #[test]
+    fn int_cursor_round_trip() {
+        let c = encode_cursor("42", "an-id");
+        assert_eq!(parse_int_cursor::<i64>(&c).unwrap(), (42, "an-id".to_owned()));
+        assert!(parse_int_cursor::<i32>(&encode_cursor("x", "an-id")).is_err());
+        assert!(parse_int_cursor::<i32>("not a cursor").is_err());
+    }
+}